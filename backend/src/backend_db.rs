@@ -4,14 +4,16 @@ use std::{fs::File, io::BufReader, path::Path};
 
 use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorInternalServerError, ErrorNotFound};
 use nanoid::nanoid;
-use polodb_core::bson::{doc, Document};
+use polodb_core::bson::{self, doc, Document};
 use polodb_core::{Collection, CollectionT, Database};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use crate::server::game::card_info::{CardInfo, CardInfoList};
+use crate::server::game::bot::BotDifficulty;
 use crate::server::game::cards::card::Card;
 use crate::server::game::game::MAX_PLAYERS;
+use crate::server::replay::Replay;
 use crate::GameId;
 
 
@@ -33,13 +35,24 @@ pub struct Lobby {
     pub users_ready: HashSet<i32>,
     /// if unlisted the lobby is not returned by /lobby/list route
     pub unlisted: bool,
+    /// if true anyone can watch the game, otherwise only the friends of the players
+    #[serde(default)]
+    pub allow_spectators: bool,
+    /// bots added to the lobby, they take the seats left by the users
+    #[serde(default)]
+    pub bots: Vec<BotDifficulty>,
     #[schema(value_type = Option<String>)]
     pub game_id: Option<GameId>
 }
 
 impl Lobby {
-    pub fn new(id: String, unlisted: bool) -> Self {
-        Self { id, users: HashSet::new(), users_ready: HashSet::new(), unlisted, game_id: None }
+    pub fn new(id: String, unlisted: bool, allow_spectators: bool) -> Self {
+        Self { id, users: HashSet::new(), users_ready: HashSet::new(), unlisted, allow_spectators, bots: Vec::new(), game_id: None }
+    }
+
+    /// Number of players of the game, bots included
+    pub fn player_count(&self) -> usize {
+        self.users.len() + self.bots.len()
     }
 
     pub fn all_users_ready(&self) -> bool {
@@ -53,7 +66,9 @@ impl Lobby {
             id: self.id.clone(),
             users: self.users.clone(),
             users_ready: self.users_ready.clone(),
-            ingame: self.game_id.is_some()
+            ingame: self.game_id.is_some(),
+            allow_spectators: self.allow_spectators,
+            bots: self.bots.clone()
         }
     }
 }
@@ -65,7 +80,9 @@ pub struct LobbyInfo {
     pub id: String,
     pub users: HashSet<i32>,
     pub users_ready: HashSet<i32>,
-    pub ingame: bool
+    pub ingame: bool,
+    pub allow_spectators: bool,
+    pub bots: Vec<BotDifficulty>
 }
 
 
@@ -81,6 +98,8 @@ pub struct LobbyPageList {
 pub struct CreateLobbyInfo {
     #[serde(default)]
     pub unlisted: bool,
+    #[serde(default)]
+    pub allow_spectators: bool,
 }
 
 
@@ -111,6 +130,10 @@ impl BackendDb {
         self.0.collection("lobby_users")
     }
 
+    pub fn replays_collection(&self) -> polodb_core::Collection<Replay> {
+        self.0.collection("replays")
+    }
+
     /// List lobbies, skipping unlisted ones
     pub fn paginate_lobby_list(&self, page: usize) -> Result<LobbyPageList, polodb_core::Error> {
         let lobbies = self.lobbies_collection();
@@ -184,6 +207,12 @@ impl BackendDb {
         } else { None }
     }
 
+    pub fn get_lobby_for_game(&self, game_id: &GameId) -> Option<Lobby> {
+        self.lobbies_collection()
+            .find_one(doc! { "game_id": game_id.to_string() })
+            .unwrap_or(None)
+    }
+
     pub fn create_lobby(&self, creator_id: i32, info: &CreateLobbyInfo) -> Result<Lobby, actix_web::Error> {
         // check if user is already in a lobby
         if self.get_lobby_for_user(creator_id).is_some() {
@@ -196,7 +225,7 @@ impl BackendDb {
 
         let lobby_id = self.generate_lobby_id().map_err(ErrorInternalServerError)?;
 
-        let mut lobby = Lobby::new(lobby_id.to_owned(), info.unlisted);
+        let mut lobby = Lobby::new(lobby_id.to_owned(), info.unlisted, info.allow_spectators);
         lobby.users.insert(creator_id);
 
         // insert in collection
@@ -221,7 +250,7 @@ impl BackendDb {
 
         if let Some(mut lobby) = lobbies.find_one(doc! { "id": lobby_id })
                 .map_err(ErrorInternalServerError)? {
-            if lobby.users.len() + 1 > MAX_PLAYERS {
+            if lobby.player_count() + 1 > MAX_PLAYERS {
                 return Err(ErrorBadRequest("Lobby is full !"));
             }
 
@@ -255,7 +284,7 @@ impl BackendDb {
         let lobbies = self.lobbies_collection();
 
         if let Some(mut lobby) = self.get_lobby_for_user(account_id) {
-            if lobby.all_users_ready() && lobby.users.len() > 1 {
+            if lobby.all_users_ready() && lobby.player_count() > 1 {
                 return Err(ErrorConflict("Can't leave because all users are ready !"));
             }
 
@@ -302,7 +331,7 @@ impl BackendDb {
                 return Err(ErrorConflict("Can't update because a game has already started !"))
             }
 
-            if lobby.all_users_ready() && lobby.users.len() > 1 {
+            if lobby.all_users_ready() && lobby.player_count() > 1 {
                 return Err(ErrorConflict("Can't update because all users are ready !"));
             }
 
@@ -324,6 +353,44 @@ impl BackendDb {
         }
     }
 
+    pub fn add_lobby_bot(&self, account_id: i32, difficulty: BotDifficulty) -> Result<Lobby, actix_web::Error> {
+        if let Some(mut lobby) = self.get_lobby_for_user(account_id) {
+            if lobby.game_id.is_some() {
+                return Err(ErrorConflict("Can't add a bot because a game has already started !"));
+            }
+
+            if lobby.player_count() + 1 > MAX_PLAYERS {
+                return Err(ErrorBadRequest("Lobby is full !"));
+            }
+
+            lobby.bots.push(difficulty);
+            self.update_lobby_bots(&lobby)?;
+
+            Ok(lobby)
+        } else {
+            Err(ErrorNotFound("User is not in a lobby !"))
+        }
+    }
+
+    /// Remove the last bot added to the lobby
+    pub fn remove_lobby_bot(&self, account_id: i32) -> Result<Lobby, actix_web::Error> {
+        if let Some(mut lobby) = self.get_lobby_for_user(account_id) {
+            if lobby.game_id.is_some() {
+                return Err(ErrorConflict("Can't remove a bot because a game has already started !"));
+            }
+
+            if lobby.bots.pop().is_none() {
+                return Err(ErrorNotFound("Lobby has no bots !"));
+            }
+
+            self.update_lobby_bots(&lobby)?;
+
+            Ok(lobby)
+        } else {
+            Err(ErrorNotFound("User is not in a lobby !"))
+        }
+    }
+
     pub fn reset_users_ready_on_game_end(&self, game_id: &GameId) -> Result<(), actix_web::Error> {
         let lobbies = self.lobbies_collection();
 
@@ -338,6 +405,17 @@ impl BackendDb {
         Ok(())
     }
 
+    pub fn save_replay(&self, replay: &Replay) -> Result<(), polodb_core::Error> {
+        self.replays_collection().insert_one(replay)?;
+
+        Ok(())
+    }
+
+    pub fn find_replay(&self, game_id: &GameId) -> Result<Option<Replay>, polodb_core::Error> {
+        self.replays_collection()
+            .find_one(doc! { "game_id": game_id.to_string() })
+    }
+
     fn set_user_lobby_index(&self, lobby_id: &LobbyId, account_id: i32) -> Result<(), polodb_core::Error> {
         let user_indexes = self.lobby_users_collection();
 
@@ -355,6 +433,20 @@ impl BackendDb {
         Ok(())
     }
 
+    fn update_lobby_bots(&self, lobby: &Lobby) -> Result<(), actix_web::Error> {
+        let bots = bson::to_bson(&lobby.bots).map_err(ErrorInternalServerError)?;
+
+        self.lobbies_collection().update_one(doc! {
+            "id": &lobby.id
+        }, doc! {
+            "$set": doc! {
+                "bots": bots
+            }
+        }).map_err(ErrorInternalServerError)?;
+
+        Ok(())
+    }
+
     fn unset_user_lobby_index(&self, account_id: i32) -> Result<(), polodb_core::Error> {
         let user_indexes = self.lobby_users_collection();
 
@@ -28,11 +28,15 @@ use utoipa::{openapi::{self, security::{HttpAuthScheme, HttpBuilder, SecuritySch
         crate::routes::game::get_lobby_info,
         crate::routes::game::join_lobby,
         crate::routes::game::leave_current_lobby,
+        crate::routes::game::add_lobby_bot,
+        crate::routes::game::remove_lobby_bot,
         crate::routes::game::get_game_session_info,
         crate::routes::game::get_current_game_session_info,
         crate::routes::game::list_game_sessions,
         crate::routes::sse::event_stream,
         crate::routes::cards::get_cards_collection,
+        crate::routes::replay::get_replay,
+        crate::routes::replay::get_replay_turn,
     ),
     components(
         schemas(
@@ -52,8 +56,15 @@ use utoipa::{openapi::{self, security::{HttpAuthScheme, HttpBuilder, SecuritySch
             crate::backend_db::CreateLobbyInfo,
             crate::routes::game::LobbyJoinInfo,
             crate::routes::game::LobbyReadyInfo,
+            crate::routes::game::LobbyBotInfo,
+            crate::server::game::bot::BotDifficulty,
             crate::server::dto::GameSessionInfo,
             crate::routes::cards::CardInfo,
+            crate::server::replay::Replay,
+            crate::server::replay::ReplayTurn,
+            crate::server::replay::ReplayAction,
+            crate::server::replay::MoveAuthor,
+            crate::server::replay::ReplayEvent,
         )
     ),
     tags(
@@ -66,6 +77,7 @@ use utoipa::{openapi::{self, security::{HttpAuthScheme, HttpBuilder, SecuritySch
         (name = "Game", description = "Game session endpoints"),
         (name = "SSE", description = "Server-Sent Events endpoints for real-time updates"),
         (name = "Cards", description = "Card endpoints"),
+        (name = "Replay", description = "Replays of finished games"),
     ),
     modifiers(&SecurityAddon)
 )]
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use core::time::Duration;
 
-use actix_web::error::ErrorNotFound;
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound};
 use actix_web::{get, HttpMessage, HttpRequest};
 use actix_web::{web, App, HttpServer, HttpResponse};
 use actix_web::middleware::Logger;
@@ -10,6 +10,8 @@ use actix_cors::Cors;
 use diesel::PgConnection;
 use diesel::r2d2;
 use polodb_core::bson::doc;
+use serde::Deserialize;
+use server::dto::responses::{PlayerProfile, Seq};
 use server::handler;
 use server::server::GameServerHandle;
 use tokio::time::Instant;
@@ -35,6 +37,8 @@ type DbPool = r2d2::Pool<r2d2::ConnectionManager<PgConnection>>;
 use tokio::task::{spawn_local, JoinHandle};
 use uuid::Uuid;
 
+use crate::backend_db::BackendDb;
+use crate::database::actions;
 use crate::docs::ApiDoc;
 use crate::email::mailer::Mailer;
 use crate::routes::sse::Broadcaster;
@@ -61,13 +65,61 @@ async fn purge_server_handlers_periodic(server_handlers: GameHandlers, period: D
 }
 
 
+/// Profile of a user who wants to watch a game.
+/// Anyone can watch if the lobby allows spectators, otherwise only the friends of the players.
+async fn get_spectator_profile(
+    account_id: i32,
+    game_id: &GameId,
+    players: &[PlayerProfile],
+    pool: web::Data<DbPool>,
+    backend_db: web::Data<BackendDb>,
+) -> Result<PlayerProfile, actix_web::Error> {
+    let allow_spectators = backend_db.get_lobby_for_game(game_id)
+        .map(|lobby| lobby.allow_spectators)
+        .unwrap_or(false);
+    let player_ids: Vec<i32> = players.iter().map(|prf| prf.id).collect();
+
+    let (account, is_friend) = web::block(move || {
+        let mut conn = pool.get().expect("couldn't get db connection from pool");
+
+        let account = actions::get_account_by_id(&mut conn, account_id)?;
+        let is_friend = actions::list_friends_for_account(&mut conn, account_id)?
+            .iter()
+            .filter(|friend| friend.status == 1)
+            .any(|friend| player_ids.contains(&friend.account1) || player_ids.contains(&friend.account2));
+
+        diesel::QueryResult::Ok((account, is_friend))
+    })
+    .await?
+    .map_err(ErrorInternalServerError)?;
+
+    if allow_spectators || is_friend {
+        Ok(PlayerProfile { id: account.id, name: account.username })
+    } else {
+        Err(ErrorForbidden("Not allowed to watch this game"))
+    }
+}
+
+
+#[derive(Deserialize)]
+struct ReconnectQuery {
+    /// sequence number of the last response received before the connection was lost
+    last_seq: Option<Seq>,
+}
+
+
 /// Handshake and start WebSocket handler with heartbeats.
+/// Users that are not players of the game join as spectators.
+/// Players that reconnect can give the `last_seq` query parameter to receive the responses they missed.
 #[get("/ws/{game_id}")]
 async fn connect_to_ws(
     req: HttpRequest,
     stream: web::Payload,
     game_handlers: web::Data<GameHandlers>,
-    path: web::Path<(GameId,)>
+    pool: web::Data<DbPool>,
+    backend_db: web::Data<BackendDb>,
+    path: web::Path<(GameId,)>,
+    query: web::Query<ReconnectQuery>
 ) -> Result<HttpResponse, actix_web::Error> {
     let account_id: i32 = req.extensions().get::<i32>()
                              .unwrap()
@@ -75,30 +127,35 @@ async fn connect_to_ws(
     
     let (game_id, ) = path.into_inner();
     
-    let game_handlers = game_handlers.lock().unwrap();
-
-    match game_handlers.get(&game_id) {
-        Some((_, handle)) => {
-
-            if handle.is_closed() {
-                return Err(ErrorNotFound("Game is over"));
-            }
-            
-            let (res, session, msg_stream) = actix_ws::handle(&req, stream)?;
-            
-            // spawn websocket handler (and don't await it) so that the response is returned immediately
-            spawn_local(handler::game_ws(
-                handle.clone(),
-                session,
-                msg_stream,
-                account_id
-            ));
-
-            Ok(res)
-
-        },
-        None => { Err(ErrorNotFound("Invalid Game Id")) }
+    let handle = match game_handlers.lock().unwrap().get(&game_id) {
+        Some((_, handle)) => handle.clone(),
+        None => { return Err(ErrorNotFound("Invalid Game Id")); }
+    };
+
+    if handle.is_closed() {
+        return Err(ErrorNotFound("Game is over"));
     }
+
+    let players = handle.get_session_info().await;
+    let spectator = if players.iter().any(|prf| prf.id == account_id) {
+        None
+    } else {
+        Some(get_spectator_profile(account_id, &game_id, &players, pool, backend_db).await?)
+    };
+
+    let (res, session, msg_stream) = actix_ws::handle(&req, stream)?;
+
+    // spawn websocket handler (and don't await it) so that the response is returned immediately
+    spawn_local(handler::game_ws(
+        handle,
+        session,
+        msg_stream,
+        account_id,
+        spectator,
+        query.last_seq
+    ));
+
+    Ok(res)
 }
 
 
@@ -164,6 +221,8 @@ async fn main() -> std::io::Result<()> {
             .configure(routes::sse::configure_routes)
             // cards
             .configure(routes::cards::configure_routes)
+            // replays
+            .configure(routes::replay::configure_routes)
 
             // ws
             .service(connect_to_ws)
@@ -3,6 +3,7 @@ pub mod auth;
 pub mod cards;
 pub mod friends;
 pub mod game;
+pub mod replay;
 pub mod settings;
 pub mod sse;
 pub mod stats;
\ No newline at end of file
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use actix_web::error::{ErrorInternalServerError, ErrorNotFound};
 use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse, Responder};
 use actix_web::{get, post, delete, patch};
@@ -11,6 +13,8 @@ use utoipa::ToSchema;
 
 use crate::routes::sse::Broadcaster;
 use crate::server::dto::{GameSessionInfo, responses::PlayerProfile};
+use crate::server::game::bot::BotDifficulty;
+use crate::server::game::player::PlayerId;
 use crate::server::server::GameServer;
 use crate::backend_db::{BackendDb, CreateLobbyInfo, Lobby, LobbyId, LobbyInfo, LobbyPageList, LOBBY_ID_LEN};
 use crate::{GameHandlers, GameId};
@@ -34,10 +38,18 @@ async fn create_game_session(
     .await?
     .map_err(ErrorInternalServerError)?;
 
-    let players: Vec<PlayerProfile> = players.iter()
+    let mut players: Vec<PlayerProfile> = players.iter()
         .map(|acc| PlayerProfile { id: acc.id, name: acc.username.clone() })
         .collect();
 
+    // bots use negative ids to not collide with accounts
+    let mut bots: HashMap<PlayerId, BotDifficulty> = HashMap::new();
+    for (i, difficulty) in lobby.bots.iter().enumerate() {
+        let bot_id = -(i as PlayerId + 1);
+        players.push(PlayerProfile { id: bot_id, name: format!("Bot {} ({})", i + 1, difficulty.name()) });
+        bots.insert(bot_id, *difficulty);
+    }
+
     let mut game_handlers = game_handlers.lock().unwrap();
 
     // generate game id and update lobby
@@ -57,7 +69,7 @@ async fn create_game_session(
 
     let (ready_tx, ready_rx) = oneshot::channel();
 
-    let (game_server, handle) = GameServer::new(players, game_id, backend_db.get_ref().clone(), ready_tx);
+    let (game_server, handle) = GameServer::new(players, bots, game_id, backend_db.get_ref().clone(), ready_tx);
     let proccess = spawn(game_server.run());
     ready_rx.await.map_err(ErrorInternalServerError)?;    // wait for ready signal
 
@@ -259,7 +271,7 @@ async fn lobby_set_ready(
     // handles errors
     let mut lobby = backend_db.update_user_ready_state(account_id, ready)?;
 
-    if lobby.all_users_ready() && lobby.users.len() > 1 {
+    if lobby.all_users_ready() && lobby.player_count() > 1 {
         // create game
         create_game_session(&mut lobby, game_handlers, pool, backend_db).await?;
 
@@ -274,6 +286,73 @@ async fn lobby_set_ready(
 }
 
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LobbyBotInfo {
+    #[serde(default)]
+    pub difficulty: BotDifficulty,
+}
+
+#[utoipa::path(
+    post,
+    path = "/lobby/current/bots",
+    request_body = LobbyBotInfo,
+    responses(
+        (status = 200, description = "Bot added to the lobby", body = Lobby),
+        (status = 404, description = "User is not in a lobby"),
+        (status = 409, description = "A game has already started"),
+        (status = 400, description = "Lobby is full"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("jwt" = [])),
+    tag = "Lobby"
+)]
+#[post("/lobby/current/bots")]
+async fn add_lobby_bot(
+    req: HttpRequest,
+    json: web::Json<LobbyBotInfo>,
+    backend_db: web::Data<BackendDb>,
+    broadcaster: web::Data<Broadcaster>
+) -> actix_web::Result<impl Responder> {
+    let account_id: i32 = *req.extensions().get::<i32>().unwrap();
+
+    // handles errors
+    let lobby = backend_db.add_lobby_bot(account_id, json.difficulty)?;
+
+    broadcaster.notify_lobby_bot_list_update(&lobby, account_id).await;
+
+    Ok(HttpResponse::Ok().json(lobby))
+}
+
+
+#[utoipa::path(
+    delete,
+    path = "/lobby/current/bots",
+    responses(
+        (status = 200, description = "Last added bot removed from the lobby", body = Lobby),
+        (status = 404, description = "User is not in a lobby or lobby has no bots"),
+        (status = 409, description = "A game has already started"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("jwt" = [])),
+    tag = "Lobby"
+)]
+#[delete("/lobby/current/bots")]
+async fn remove_lobby_bot(
+    req: HttpRequest,
+    backend_db: web::Data<BackendDb>,
+    broadcaster: web::Data<Broadcaster>
+) -> actix_web::Result<impl Responder> {
+    let account_id: i32 = *req.extensions().get::<i32>().unwrap();
+
+    // handles errors
+    let lobby = backend_db.remove_lobby_bot(account_id)?;
+
+    broadcaster.notify_lobby_bot_list_update(&lobby, account_id).await;
+
+    Ok(HttpResponse::Ok().json(lobby))
+}
+
+
 #[utoipa::path(
     post,
     path = "/lobby/current/leave",
@@ -462,6 +541,8 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         .service(get_lobby_info)
         .service(join_lobby)
         .service(leave_current_lobby)
+        .service(add_lobby_bot)
+        .service(remove_lobby_bot)
 
         .service(get_game_session_info)
         .service(get_current_game_session_info)
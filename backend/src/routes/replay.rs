@@ -0,0 +1,86 @@
+use actix_web::error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound};
+use actix_web::{get, web, HttpMessage, HttpRequest, HttpResponse, Responder};
+
+use crate::backend_db::BackendDb;
+use crate::server::replay::{Replay, ReplayTurn};
+use crate::GameId;
+
+
+/// Replay of the game, only available to the players of the game as it keeps the hidden cards
+fn find_replay_for_account(backend_db: &BackendDb, game_id: &GameId, account_id: i32) -> actix_web::Result<Replay> {
+    let replay = backend_db.find_replay(game_id)
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| ErrorNotFound(format!("No replay for game {}", game_id)))?;
+
+    if !replay.players.iter().any(|p| p.id == account_id) {
+        return Err(ErrorForbidden("Only the players of the game can watch its replay"));
+    }
+
+    Ok(replay)
+}
+
+#[utoipa::path(
+    get,
+    path = "/replay/{game_id}",
+    params(("game_id" = String, Path, description = "Game ID (UUID)")),
+    responses(
+        (status = 200, description = "Replay of the game", body = Replay),
+        (status = 403, description = "Not a player of the game"),
+        (status = 404, description = "No replay for this game"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("jwt" = [])),
+    tag = "Replay"
+)]
+#[get("/replay/{game_id}")]
+async fn get_replay(
+    req: HttpRequest,
+    path: web::Path<(GameId,)>,
+    backend_db: web::Data<BackendDb>,
+) -> actix_web::Result<impl Responder> {
+    let account_id: i32 = *req.extensions().get::<i32>().unwrap();
+    let (game_id,) = path.into_inner();
+
+    let replay = find_replay_for_account(&backend_db, &game_id, account_id)?;
+    Ok(HttpResponse::Ok().json(replay))
+}
+
+
+#[utoipa::path(
+    get,
+    path = "/replay/{game_id}/turn/{turn}",
+    params(
+        ("game_id" = String, Path, description = "Game ID (UUID)"),
+        ("turn" = u32, Path, description = "Turn index (the first turn is 0)")
+    ),
+    responses(
+        (status = 200, description = "Actions and events of the turn", body = ReplayTurn),
+        (status = 403, description = "Not a player of the game"),
+        (status = 404, description = "No replay for this game or turn not played"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("jwt" = [])),
+    tag = "Replay"
+)]
+#[get("/replay/{game_id}/turn/{turn}")]
+async fn get_replay_turn(
+    req: HttpRequest,
+    path: web::Path<(GameId, u32)>,
+    backend_db: web::Data<BackendDb>,
+) -> actix_web::Result<impl Responder> {
+    let account_id: i32 = *req.extensions().get::<i32>().unwrap();
+    let (game_id, turn) = path.into_inner();
+
+    let replay = find_replay_for_account(&backend_db, &game_id, account_id)?;
+
+    match replay.turn(turn) {
+        Some(replay_turn) => Ok(HttpResponse::Ok().json(replay_turn)),
+        None => Err(ErrorNotFound(format!("Turn {} was not played ({} turns)", turn, replay.turn_count)))
+    }
+}
+
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_replay)
+        .service(get_replay_turn);
+}
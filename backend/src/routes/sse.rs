@@ -16,6 +16,7 @@ use serde::Serialize;
 
 use crate::database::models::Friend;
 use crate::backend_db::Lobby;
+use crate::server::game::bot::BotDifficulty;
 use crate::GameId;
 
 
@@ -26,6 +27,7 @@ pub enum SseMessage {
     FriendshipDeleted { id: i32 },
     LobbyUserListChange { users: HashSet<i32> },
     LobbyUserReadyChange { user: i32, ready: bool },
+    LobbyBotListChange { bots: Vec<BotDifficulty> },
     GameStarted { game_id: GameId }
 }
 
@@ -174,6 +176,23 @@ impl Broadcaster {
 
     }
 
+    pub async fn notify_lobby_bot_list_update(&self, lobby: &Lobby, skip_id: i32) {
+        let clients = self.inner.lock().clients.clone();
+        
+        let msg = SseMessage::LobbyBotListChange { bots: lobby.bots.clone() };
+
+        let send_futures = clients
+            .iter()
+            .filter(|client|
+                client.account_id != skip_id
+                && lobby.users.contains(&client.account_id))
+            .map(|client| msg.send(&client.tx));
+
+        // try to send to all clients
+        let _ = future::join_all(send_futures).await;
+
+    }
+
     pub async fn notify_lobby_game_started(&self, lobby: &Lobby) {
         let clients = self.inner.lock().clients.clone();
         
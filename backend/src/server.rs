@@ -1,4 +1,6 @@
 pub mod server;
 pub mod handler;
 pub mod dto;
-pub mod game;
\ No newline at end of file
+pub mod game;
+pub mod events;
+pub mod replay;
\ No newline at end of file
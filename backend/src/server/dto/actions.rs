@@ -1,10 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+use crate::server::game::cards::card::Element;
+use crate::server::game::choice::Choice;
 use crate::server::game::player::PlayerId;
 
 
 /// JSON structures for client messages
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 // Tells serde to try to deserialyze the user's JSON action to any of the following structures.
 // The JSON must contain the key "type" with a string matching the enum variant's name.
 #[serde(tag = "type")]
@@ -12,6 +14,16 @@ pub enum UserAction {
     /// The user plays a card on targetted opponents
     /// Dice rolls are handled by the server (no actual dice roll, the client only sees the result of the roll)
     PlayCard {card_index: usize, targets: Vec<PlayerId>},
+    /// The user answers the choice required by the card being played
+    ResolveChoice {
+        choice: Choice,
+    },
+    /// The user ends a turn where more cards could be played
+    EndTurn {},
+    /// The user chooses their element for the elemental affinity (if the affinities use chosen elements)
+    ChooseElement {
+        element: Element,
+    },
     /// The user wants to draw a card
     /// TODO REMOVE
     DrawCard {},
@@ -6,8 +6,10 @@ use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::UnboundedSender;
 use utoipa::ToSchema;
 
-use crate::server::game::buffs::BuffVariant;
-use crate::server::game::cards::card::CardId;
+use crate::server::game::buffs::{BuffStatus, BuffVariant, TickTime};
+use crate::server::game::cards::card::{CardId, Element};
+use crate::server::game::choice::{Choice, ChoiceRequest};
+use crate::server::game::game::Order;
 use crate::server::game::play_info::PlayAction;
 use crate::server::game::player::PlayerId;
 
@@ -26,7 +28,9 @@ pub struct OpponentState {
     pub health: u32,
     pub card_count: u32,
     pub discard_cards: Vec<CardId>,
-    pub buffs: Vec<BuffVariant>
+    pub buffs: Vec<BuffStatus>,
+    /// element chosen for the elemental affinity
+    pub element: Option<Element>,
 }
 
 
@@ -38,11 +42,35 @@ pub struct GameStateForPlayer {
     pub health: u32,
     pub cards: Vec<CardId>,
     pub discard_cards: Vec<CardId>,
-    pub buffs: Vec<BuffVariant>,
+    pub buffs: Vec<BuffStatus>,
+    /// element chosen for the elemental affinity
+    pub element: Option<Element>,
     pub opponents: Vec<OpponentState>,
     pub cards_in_pile: u32
 }
 
+/// Public view of the game, for spectators (no hand contents)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GameStateForSpectator {
+    pub current_player_turn: PlayerId,
+    #[serde(with = "ts_seconds")]   // needed to serialize a DateTime with serde
+    pub current_player_turn_end: DateTime<Utc>,
+    pub players: Vec<OpponentState>,
+    pub cards_in_pile: u32
+}
+
+impl GameStateForSpectator {
+    pub fn to_server_response(&self) -> ServerResponse {
+        ServerResponse::SpectatorStatus {
+            current_player_turn: self.current_player_turn,
+            current_player_turn_end: self.current_player_turn_end,
+            players: self.players.clone(),
+            cards_in_pile: self.cards_in_pile
+        }
+    }
+}
+
+
 impl GameStateForPlayer {
     pub fn to_server_response(&self) -> ServerResponse {
         ServerResponse::GameStatus {
@@ -52,6 +80,7 @@ impl GameStateForPlayer {
             cards: self.cards.clone(),
             discard_cards: self.discard_cards.clone(),
             buffs: self.buffs.clone(),
+            element: self.element,
             opponents: self.opponents.clone(),
             cards_in_pile: self.cards_in_pile
         }
@@ -60,16 +89,31 @@ impl GameStateForPlayer {
 
 
 /// JSON structures for server responses
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum ServerResponse {
     Message {message: String},
     /// Sent when player connects to WebSocket
     SessionInfo {
-        /// which player id is assigned to the client
+        /// version of the format of the responses (PROTOCOL_VERSION)
+        protocol_version: u32,
+        /// which player id is assigned to the client (account id for spectators)
         id: PlayerId,
         /// all players info
-        players: Vec<PlayerProfile>
+        players: Vec<PlayerProfile>,
+        /// users watching the game
+        spectators: Vec<PlayerProfile>,
+    },
+
+    /// message sent by a spectator, only received by spectators
+    SpectatorMessage {
+        spectator_id: PlayerId,
+        message: String
+    },
+
+    /// a spectator joined or left the game
+    SpectatorsChanged {
+        spectators: Vec<PlayerProfile>
     },
 
     /// Game Status (personnalised for each client)
@@ -80,10 +124,21 @@ pub enum ServerResponse {
         health: u32,
         cards: Vec<CardId>,
         discard_cards: Vec<CardId>,
-        buffs: Vec<BuffVariant>,
+        buffs: Vec<BuffStatus>,
+        /// element chosen for the elemental affinity
+        element: Option<Element>,
         opponents: Vec<OpponentState>,
         cards_in_pile: u32
     },
+
+    /// Public game status, sent to spectators instead of GameStatus
+    SpectatorStatus {
+        current_player_turn: PlayerId,
+        #[serde(with = "ts_seconds")]   // needed to serialize a DateTime with serde
+        current_player_turn_end: DateTime<Utc>,
+        players: Vec<OpponentState>,
+        cards_in_pile: u32
+    },
     
     // Game Actions
 
@@ -102,6 +157,61 @@ pub enum ServerResponse {
         card_id: CardId
     },
 
+    /// the played card went back to the player's hand instead of the discard pile
+    ReturnCardToHand {
+        player_id: PlayerId,
+        card_id: CardId,
+        /// new index of the card in player's hand
+        hand_index: u32,
+    },
+
+    /// cards in the hand of a player shown to another player.
+    /// Only the player `to` receives the card ids, other clients receive -1 for each card.
+    RevealHand {
+        player_id: PlayerId,
+        to: PlayerId,
+        cards: Vec<CardId>,
+    },
+
+    /// the player has to make a choice for the card being played.
+    /// The default choice is used if the player does not answer before `turn_end`.
+    ChoiceRequired {
+        player_id: PlayerId,
+        request: ChoiceRequest,
+        default: Choice,
+        #[serde(with = "ts_seconds")]
+        turn_end: DateTime<Utc>
+    },
+
+    /// the player can play more cards in this turn.
+    /// `required_cards` are the hand indices of the cards that must be played before the turn ends
+    /// (only sent to the player, empty for the other players). They are played automatically at `turn_end`.
+    ContinueTurn {
+        player_id: PlayerId,
+        required_cards: Vec<u32>,
+        #[serde(with = "ts_seconds")]
+        turn_end: DateTime<Utc>
+    },
+
+    /// the direction of play changed
+    TurnOrderChanged {
+        order: Order,
+    },
+
+    /// the player skips their turn because of a debuff
+    TurnSkipped {
+        player_id: PlayerId,
+        /// debuff that made the player skip their turn
+        reason: BuffVariant,
+    },
+
+    /// periodic buffs of the player (damage over time, regeneration) applied at the start or end of their turn
+    StatusTick {
+        player_id: PlayerId,
+        tick: TickTime,
+        action: PlayAction,
+    },
+
     /// notify client of turn change
     ChangeTurn {
         player_id: PlayerId,
@@ -117,7 +227,13 @@ pub enum ServerResponse {
     /// send list of active buffs for the player
     PlayerBuffStatus {
         player_id: PlayerId,
-        buffs: Vec<BuffVariant>
+        buffs: Vec<BuffStatus>
+    },
+
+    /// the player chose their element for the elemental affinity
+    ElementChosen {
+        player_id: PlayerId,
+        element: Element,
     },
 
     /// notify client of game end
@@ -126,6 +242,24 @@ pub enum ServerResponse {
     }
 }
 
+/// Version of the format of the responses, sent in SessionInfo.
+/// - 1: responses of the game as they are
+/// - 2: responses of the game are SequencedResponse, with a "seq" key
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Sequence number of the responses sent by the game server, increasing for each event of the game
+pub type Seq = u64;
+
+/// Response with its sequence number, as sent to the clients
+/// (the "seq" key is added next to the "type" key of the response)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SequencedResponse {
+    pub seq: Seq,
+    #[serde(flatten)]
+    pub response: ServerResponse,
+}
+
+
 impl ServerResponse {
     /// send as text through the Session
     pub async fn send(&self, session: &mut Session) -> Result<(), Closed> {
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+
+use tokio::sync::mpsc;
+
+use super::dto::responses::{SequencedResponse, Seq, ServerResponse};
+use super::game::player::PlayerId;
+use super::server::Msg;
+
+
+/// Amount of messages kept for the clients that reconnect (all receivers included)
+pub const EVENT_BUFFER_SIZE: usize = 1024;
+
+
+#[derive(Debug)]
+struct BufferedEvent {
+    seq: Seq,
+    receiver_id: PlayerId,
+    msg: Msg,
+}
+
+/// Numbers the responses sent by the game server and keeps the last ones,
+/// so that a client that reconnects receives the events it missed.
+#[derive(Debug)]
+pub struct EventBuffer {
+    last_seq: Seq,
+    /// highest sequence number removed from the buffer
+    dropped_seq: Seq,
+    events: VecDeque<BufferedEvent>,
+}
+
+impl EventBuffer {
+    pub fn new() -> Self {
+        Self { last_seq: 0, dropped_seq: 0, events: VecDeque::with_capacity(EVENT_BUFFER_SIZE) }
+    }
+
+    /// Sequence number of a new event.
+    /// The personalised versions of an event sent to each client share the same number.
+    pub fn next_seq(&mut self) -> Seq {
+        self.last_seq += 1;
+        self.last_seq
+    }
+
+    /// Keep the response with its sequence number in the buffer, and send it if the receiver is connected
+    pub fn push(&mut self, seq: Seq, receiver_id: PlayerId, tx: Option<&mpsc::UnboundedSender<Msg>>, response: ServerResponse) {
+        let msg = match serde_json::to_string(&SequencedResponse { seq, response }) {
+            Ok(msg) => msg,
+            Err(e) => {
+                log::error!("Error when serializing response {}: {}", seq, e);
+                return;
+            }
+        };
+        if let Some(tx) = tx {
+            let _ = tx.send(msg.clone());
+        }
+
+        if self.events.len() >= EVENT_BUFFER_SIZE {
+            if let Some(event) = self.events.pop_front() {
+                self.dropped_seq = event.seq;
+            }
+        }
+        self.events.push_back(BufferedEvent { seq, receiver_id, msg });
+    }
+
+    /// Messages sent to the receiver after `last_seen`, in order.
+    /// None if some of them are no longer in the buffer (or `last_seen` was never sent).
+    pub fn missed_events(&self, receiver_id: PlayerId, last_seen: Seq) -> Option<Vec<Msg>> {
+        if last_seen < self.dropped_seq || last_seen > self.last_seq {
+            return None;
+        }
+
+        Some(self.events.iter()
+            .filter(|event| event.seq > last_seen && event.receiver_id == receiver_id)
+            .map(|event| event.msg.clone())
+            .collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seq_of(msg: &Msg) -> Seq {
+        serde_json::from_str::<SequencedResponse>(msg).unwrap().seq
+    }
+
+    #[test]
+    fn missed_events_after_wrap() {
+        let mut events = EventBuffer::new();
+        let total = EVENT_BUFFER_SIZE as Seq + 76;
+        for _ in 0..total {
+            let seq = events.next_seq();
+            events.push(seq, 1, None, ServerResponse::Message { message: seq.to_string() });
+        }
+
+        // the first 76 events were dropped
+        assert!(events.missed_events(1, 75).is_none());
+        let missed = events.missed_events(1, 76).unwrap();
+        assert_eq!(missed.len(), EVENT_BUFFER_SIZE);
+        assert_eq!(seq_of(&missed[0]), 77);
+        assert_eq!(seq_of(missed.last().unwrap()), total);
+
+        let missed = events.missed_events(1, total - 2).unwrap();
+        assert_eq!(missed.iter().map(seq_of).collect::<Vec<Seq>>(), vec![total - 1, total]);
+        assert!(events.missed_events(1, total).unwrap().is_empty());
+        assert!(events.missed_events(1, total + 1).is_none());
+        // other receivers only get their own events
+        assert!(events.missed_events(2, 76).unwrap().is_empty());
+    }
+}
@@ -7,4 +7,7 @@ pub mod eval;
 pub mod modifiers;
 pub mod buffs;
 pub mod player;
-pub mod play_info;
\ No newline at end of file
+pub mod play_info;
+pub mod choice;
+pub mod affinity;
+pub mod bot;
\ No newline at end of file
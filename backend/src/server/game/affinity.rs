@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::cards::card::Element;
+use super::player::Player;
+
+
+/// Damage percentage when the elements have no affinity
+pub const NEUTRAL_AFFINITY: u32 = 100;
+
+
+/// Where the element of the player receiving the damage comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub enum AffinitySource {
+    /// most common element in the hand of the player (no element if several elements are tied)
+    #[default]
+    Hand,
+    /// element chosen by the player during their turn
+    Chosen,
+}
+
+
+/// Damage multipliers between the elements, loaded from affinities.json
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AffinityTable {
+    #[serde(default)]
+    pub source: AffinitySource,
+    /// damage percentage by element of the card, then by element of the player receiving the damage.
+    /// Missing pairs are neutral.
+    pub multipliers: HashMap<Element, HashMap<Element, u32>>,
+}
+
+impl AffinityTable {
+    /// Element of the player used for the affinity
+    pub fn get_player_element(&self, player: &Player) -> Option<Element> {
+        match self.source {
+            AffinitySource::Hand => {
+                let mut counts: HashMap<Element, usize> = HashMap::new();
+                for card in player.hand_cards.iter() {
+                    *counts.entry(card.get_element()).or_default() += 1;
+                }
+
+                let max = counts.values().max()?;
+                let elements: Vec<Element> = counts.iter()
+                    .filter(|(_, count)| *count == max)
+                    .map(|(&element, _)| element)
+                    .collect();
+
+                if elements.len() == 1 { Some(elements[0]) } else { None }
+            }
+            AffinitySource::Chosen => player.element,
+        }
+    }
+
+    /// Damage percentage of a card of the element on the player
+    pub fn get_multiplier(&self, element: Element, player: &Player) -> u32 {
+        self.get_player_element(player)
+            .and_then(|player_element| self.multipliers.get(&element)?.get(&player_element).copied())
+            .unwrap_or(NEUTRAL_AFFINITY)
+    }
+}
+
+
+/// Load the affinity table from the JSON file at AFFINITIES_FILE_PATH.
+/// The table is optional, None is returned if the variable is not set.
+pub fn load_affinity_table() -> Result<Option<AffinityTable>, String> {
+    let path = match std::env::var("AFFINITIES_FILE_PATH") {
+        Ok(path) => path,
+        Err(_) => { return Ok(None); }
+    };
+
+    if !Path::new(&path).exists() {
+        return Err(format!("JSON file for affinities not found ({})", path));
+    }
+
+    let file = File::open(&path).map_err(|_| "Could not open JSON file")?;
+    let reader = BufReader::new(file);
+
+    let table: AffinityTable = serde_json::from_reader(reader).map_err(|_| "Error reading JSON file")?;
+
+    Ok(Some(table))
+}
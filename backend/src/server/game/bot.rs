@@ -0,0 +1,268 @@
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::cards::card::{Card, TargetType};
+use super::game::{Game, GameRng, GameState, DRAW_CARD_LIMIT};
+use super::player::PlayerId;
+
+
+/// Score of a move that wins the game
+const WIN_SCORE: i32 = 1000;
+/// Amount of simulations of each move used by the Lookahead bot, to average the dice rolls of the modifiers
+const LOOKAHEAD_SAMPLES: u64 = 4;
+
+
+/// How a bot chooses its moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default, ToSchema)]
+pub enum BotDifficulty {
+    /// plays a random valid card
+    Random,
+    /// plays the card that deals the most damage (or heals the most)
+    #[default]
+    Greedy,
+    /// plays the card with the best average outcome over several dice rolls,
+    /// counting the cards it can play afterwards in the same turn
+    Lookahead,
+}
+
+/// Move chosen by a bot for the current turn
+#[derive(Debug, Clone, PartialEq)]
+pub enum BotMove {
+    PlayCard { card_index: usize, targets: Vec<PlayerId> },
+    DrawCard,
+    EndTurn,
+}
+
+impl BotDifficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BotDifficulty::Random => "Random",
+            BotDifficulty::Greedy => "Greedy",
+            BotDifficulty::Lookahead => "Lookahead",
+        }
+    }
+
+    /// Choose the move of the current player of the game.
+    /// Moves are tried on copies of the game where the hidden cards are dealt again (see `hide_cards`)
+    /// and the RNG is re-seeded with `seed`, so that the bot can't see the cards of the opponents,
+    /// the order of the pile or the next dice rolls.
+    /// Returns None if the player can't do anything.
+    pub fn choose_move(&self, game: &Game, seed: u64) -> Option<BotMove> {
+        if !matches!(game.state, GameState::InGame) || game.pending_choice.is_some() {
+            return None;
+        }
+
+        let mut rng = GameRng::seed_from_u64(seed);
+        let view = hide_cards(game, &mut rng);
+        let game = &view;
+        let mut plays = valid_plays(game, &mut rng);
+
+        let best = match self {
+            BotDifficulty::Random => {
+                plays.shuffle(&mut rng);
+                plays.into_iter().next()
+            }
+            BotDifficulty::Greedy => {
+                best_play(game, plays, |play| simulate(game, play, rng.random()).map(|sim| evaluate(game, &sim)))
+            }
+            BotDifficulty::Lookahead => {
+                best_play(game, plays, |play| lookahead_score(game, play, &mut rng))
+            }
+        };
+
+        match best {
+            Some(play) => Some(play),
+            // cards required by PlayAllCards buffs are played automatically at the end of the turn
+            None if game.allows_more_plays() => Some(BotMove::EndTurn),
+            None if can_draw(game) => Some(BotMove::DrawCard),
+            None => None
+        }
+    }
+}
+
+
+/// Copy of the game as known by the current player: the cards of the pile and of the hands of the opponents
+/// are shuffled together and dealt again, each opponent keeping the same amount of cards
+fn hide_cards(game: &Game, rng: &mut GameRng) -> Game {
+    let mut view = game.clone();
+    let player_index = view.current_player_turn;
+
+    let mut hidden: Vec<Box<dyn Card>> = std::mem::take(&mut view.pile);
+    for (i, player) in view.players.iter_mut().enumerate() {
+        if i != player_index {
+            hidden.append(&mut player.hand_cards);
+        }
+    }
+    hidden.shuffle(rng);
+
+    for (i, player) in view.players.iter_mut().enumerate() {
+        if i != player_index {
+            let count = game.players[i].hand_cards.len();
+            player.hand_cards = hidden.split_off(hidden.len() - count);
+        }
+    }
+    view.pile = hidden;
+
+    view
+}
+
+/// Card plays accepted by the game for the current player.
+/// When the player must play some cards (PlayAllCards buffs), only those cards are tried.
+fn valid_plays(game: &Game, rng: &mut GameRng) -> Vec<BotMove> {
+    let player = &game.players[game.current_player_turn];
+    let opponents: Vec<PlayerId> = game.players.iter()
+        .filter(|p| p.id != player.id && p.health > 0)
+        .map(|p| p.id)
+        .collect();
+
+    let required = player.required_cards();
+    let card_indices: Vec<usize> = if required.is_empty() {
+        (0..player.hand_cards.len()).collect()
+    } else { required };
+
+    let mut plays = Vec::new();
+    for card_index in card_indices {
+        let mut target_sets: Vec<Vec<PlayerId>> = vec![Vec::new()];
+        match player.hand_cards[card_index].get_target_type() {
+            TargetType::Single => {
+                target_sets.extend(opponents.iter().map(|&id| vec![id]));
+            }
+            TargetType::Multiple { max } => {
+                target_sets.extend(opponents.iter().map(|&id| vec![id]));
+                if opponents.len() > 1 {
+                    target_sets.push(opponents.iter().copied().take(max).collect());
+                }
+            }
+            TargetType::All => {
+                target_sets.push(opponents.clone());
+            }
+        }
+
+        for targets in target_sets {
+            let play = BotMove::PlayCard { card_index, targets };
+            if simulate(game, &play, rng.random()).is_some() {
+                plays.push(play);
+            }
+        }
+    }
+
+    plays
+}
+
+fn can_draw(game: &Game) -> bool {
+    let player = &game.players[game.current_player_turn];
+    player.hand_cards.len() < DRAW_CARD_LIMIT && !game.pile.is_empty()
+}
+
+/// Play with the best score, if it is better than drawing a card
+fn best_play(game: &Game, plays: Vec<BotMove>, mut score: impl FnMut(&BotMove) -> Option<i32>) -> Option<BotMove> {
+    let best = plays.into_iter()
+        .filter_map(|play| score(&play).map(|s| (s, play)))
+        .max_by_key(|(s, _)| *s);
+
+    match best {
+        // a card that harms the player is only played if it has to be
+        Some((s, _)) if s < 0 && can_draw(game) && !game.allows_more_plays() => None,
+        Some((_, play)) => Some(play),
+        None => None
+    }
+}
+
+/// Game after the play, with the default choice if the card needs one.
+/// None if the play is refused.
+fn simulate(game: &Game, play: &BotMove, seed: u64) -> Option<Game> {
+    let mut sim = game.clone();
+    sim.rng = GameRng::seed_from_u64(seed);
+    let player_id = sim.current_player_id();
+
+    match play {
+        BotMove::PlayCard { card_index, targets } => {
+            sim.play_card(player_id, *card_index, targets.clone()).ok()?;
+            if sim.pending_choice.is_some() {
+                sim.resolve_choice(player_id, None).ok()?;
+            }
+        }
+        BotMove::DrawCard => { sim.draw_card(player_id).ok()?; }
+        BotMove::EndTurn => { sim.end_turn(player_id).ok()?; }
+    }
+
+    Some(sim)
+}
+
+/// Average score of the play over several dice rolls and deals of the hidden cards,
+/// adding the score of the best card that can be played next in the same turn
+fn lookahead_score(game: &Game, play: &BotMove, rng: &mut GameRng) -> Option<i32> {
+    let mut total = 0;
+    for _ in 0..LOOKAHEAD_SAMPLES {
+        let sim = simulate(&hide_cards(game, rng), play, rng.random())?;
+        let mut score = evaluate(game, &sim);
+
+        if sim.allows_more_plays() {
+            let next_best = valid_plays(&sim, rng).iter()
+                .filter_map(|next| simulate(&sim, next, rng.random()))
+                .map(|next_sim| evaluate(&sim, &next_sim))
+                .max()
+                .unwrap_or(0);
+            score += next_best;
+        }
+
+        total += score;
+    }
+
+    Some(total / LOOKAHEAD_SAMPLES as i32)
+}
+
+/// Score of the game after a move for the player who made it:
+/// damage dealt to the opponents and health gained
+fn evaluate(before: &Game, after: &Game) -> i32 {
+    let player_index = before.current_player_turn;
+    let player_id = before.players[player_index].id;
+
+    if let GameState::EndGame { winner_id } = after.state {
+        return if winner_id == player_id { WIN_SCORE } else { -WIN_SCORE };
+    }
+
+    before.players.iter().zip(after.players.iter())
+        .map(|(b, a)| {
+            if b.id == player_id { a.health - b.health } else { b.health - a.health }
+        })
+        .sum()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::game::cards::card::CardId;
+    use crate::server::game::game::test_utils::make_seeded_game;
+
+    fn card_ids<'a>(cards: impl Iterator<Item = &'a Box<dyn Card>>) -> Vec<CardId> {
+        let mut ids: Vec<CardId> = cards.map(|c| c.get_id()).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn hidden_cards_are_dealt_again() {
+        let game = make_seeded_game(0);
+
+        let view = hide_cards(&game, &mut GameRng::seed_from_u64(1));
+        let player_index = game.current_player_turn;
+        let hidden = |g: &Game| card_ids(g.pile.iter().chain(g.players.iter()
+            .enumerate()
+            .filter(|(i, _)| *i != player_index)
+            .flat_map(|(_, p)| p.hand_cards.iter())));
+
+        assert_eq!(card_ids(game.players[player_index].hand_cards.iter()), card_ids(view.players[player_index].hand_cards.iter()));
+        for (before, after) in game.players.iter().zip(view.players.iter()) {
+            assert_eq!(before.hand_cards.len(), after.hand_cards.len());
+        }
+        assert_eq!(hidden(&game), hidden(&view));
+        assert_ne!(
+            game.pile.iter().map(|c| c.get_id()).collect::<Vec<CardId>>(),
+            view.pile.iter().map(|c| c.get_id()).collect::<Vec<CardId>>()
+        );
+    }
+}
@@ -14,17 +14,88 @@ pub enum BuffType {
     /// Play all cards in one turn matching the element
     /// If no elements, kinds or stars defined, it behaves as if all variants are valid.
     PlayAllCards { elements: Vec<Element>, kinds: Vec<Kind>, stars: Vec<Stars> },
+    /// The player skips their next turn
+    SkipTurn,
+    /// Damage taken is reduced by `value`, or by `value` percent if `percent` is true
+    DamageReduction { value: u32, percent: bool },
+    /// Damage taken is absorbed until the points are depleted
+    Shield { points: u32 },
+    /// The next hit taken is cancelled
+    Immunity,
+    /// `percent` percent of the damage taken (before reduction) is dealt back to the attacker
+    Reflect { percent: u32 },
+    /// The player takes damage at the start or end of each of their turns
+    DamageOverTime { amount: u32, kind: DotKind, tick: TickTime },
+    /// The player heals at the start or end of each of their turns
+    Regeneration { amount: u32, tick: TickTime },
+}
+
+
+/// Moment a periodic buff applies, relative to the turn of its owner
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+pub enum TickTime {
+    #[default]
+    TurnStart,
+    TurnEnd,
+}
+
+
+/// Kind of damage over time, only changes the effect shown by clients
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Default)]
+pub enum DotKind {
+    #[default]
+    Burn,
+    Poison,
+}
+
+
+/// Buff types without their values, used to select buffs
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum BuffKind {
+    Attack,
+    TargetAll,
+    PlayAllCards,
+    SkipTurn,
+    DamageReduction,
+    Shield,
+    Immunity,
+    Reflect,
+    DamageOverTime,
+    Regeneration,
+}
+
+impl BuffType {
+    pub fn get_kind(&self) -> BuffKind {
+        match self {
+            BuffType::Attack { .. } => BuffKind::Attack,
+            BuffType::TargetAll => BuffKind::TargetAll,
+            BuffType::PlayAllCards { .. } => BuffKind::PlayAllCards,
+            BuffType::SkipTurn => BuffKind::SkipTurn,
+            BuffType::DamageReduction { .. } => BuffKind::DamageReduction,
+            BuffType::Shield { .. } => BuffKind::Shield,
+            BuffType::Immunity => BuffKind::Immunity,
+            BuffType::Reflect { .. } => BuffKind::Reflect,
+            BuffType::DamageOverTime { .. } => BuffKind::DamageOverTime,
+            BuffType::Regeneration { .. } => BuffKind::Regeneration,
+        }
+    }
 }
 
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub enum BuffLifeTime {
-    /// Buff is considered used at the end of the turn, regardless if it was used or not.
+    /// Buff is considered used at the end of the next turn of the owner, regardless if it was used or not.
+    /// Buffs that can change their lifetime are granted as `Turns { delay: 1, turns: 1 }`.
     UntilNextTurnEnd,
     /// Buff is considered used at the end of the turn if it was used by a card.
     // UntilTurnEndIfUsed,
     /// Buff is considered used when used by a card. It does not apply for the next actions in the turn.
     UntilUsed,
+    /// Buff becomes active after `delay` turns of the owner, and expires after `turns` turns of the owner once active.
+    /// The values are decreased at the start of each turn of the owner.
+    Turns { delay: u32, turns: u32 },
+    /// Buff is removed once it applied `ticks` times (periodic buffs).
+    Ticks { ticks: u32 },
 }
 
 
@@ -33,10 +104,24 @@ pub enum BuffLifeTime {
 pub trait Buff: Sync + Send + Debug + BuffClone {
     fn get_type(&self) -> BuffType;
     fn get_lifetime(&self) -> BuffLifeTime { BuffLifeTime::UntilNextTurnEnd }
+    /// Only for buffs with a lifetime that can be changed
+    fn set_lifetime(&mut self, _lifetime: BuffLifeTime) {}
+
+    /// A buff waiting for its delay is not applied
+    fn is_active(&self) -> bool {
+        match self.get_lifetime() {
+            BuffLifeTime::Turns { delay, turns: _ } => delay == 0,
+            _ => true
+        }
+    }
 
     // can't use Box<dyn Card> because it can't be called by play() for default impl in Card
     // use element, kind, stars directly instead
     fn is_applicable(&self, card_element: Element, card_kind: Kind, card_stars: Stars) -> bool {
+        if !self.is_active() {
+            return false;
+        }
+
         match self.get_type() {
             BuffType::Attack { value: _, op: _, elements, kinds, stars } => {
                 // no elements, kinds or stars defined -> ok (all)
@@ -63,8 +148,28 @@ pub trait Buff: Sync + Send + Debug + BuffClone {
         }
     }
 
+    /// Reduce the damage taken by the owner of the buff.
+    /// Returns the remaining damage, and true if the buff is depleted (it is removed).
+    fn mitigate(&mut self, amount: u32) -> (u32, bool) {
+        (amount, false)
+    }
+
     /// Convert the Buff to a BuffVariant to be able to serialize it with serde (not implemented for Box<dyn Buff>)
     fn as_variant(&self) -> BuffVariant;
+
+    /// Buff data sent to clients
+    fn as_status(&self) -> BuffStatus {
+        BuffStatus { buff: self.as_variant(), active: self.is_active() }
+    }
+}
+
+
+/// Buff sent to clients, with its state (a buff is pending while it waits for its delay)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BuffStatus {
+    #[serde(flatten)]
+    pub buff: BuffVariant,
+    pub active: bool,
 }
 
 // Allow Box<dyn Buff> clonning
@@ -89,6 +194,21 @@ impl Clone for Box<dyn Buff> {
 }
 
 
+/// Removes buffs from the targets of a card
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Dispel {
+    /// kinds of buffs to remove, all buffs are removed if empty
+    #[serde(default)]
+    pub kinds: Vec<BuffKind>,
+}
+
+impl Dispel {
+    pub fn is_matching(&self, buff: &dyn Buff) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&buff.get_type().get_kind())
+    }
+}
+
+
 /// Enum to use to deserialize the different buffs from cards.json
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type")]
@@ -96,6 +216,13 @@ pub enum BuffVariant {
     AttackBuff(AttackBuff),
     TargetAllBuff(TargetAllBuff),
     PlayAllCardsBuff(PlayAllCardsBuff),
+    SkipTurnDebuff(SkipTurnDebuff),
+    DamageReductionBuff(DamageReductionBuff),
+    ShieldBuff(ShieldBuff),
+    ImmunityBuff(ImmunityBuff),
+    ReflectBuff(ReflectBuff),
+    DamageOverTimeDebuff(DamageOverTimeDebuff),
+    RegenerationBuff(RegenerationBuff),
 }
 
 impl BuffVariant {
@@ -104,6 +231,13 @@ impl BuffVariant {
             BuffVariant::AttackBuff(b) => Box::new(b),
             BuffVariant::TargetAllBuff(b) => Box::new(b),
             BuffVariant::PlayAllCardsBuff(b) => Box::new(b),
+            BuffVariant::SkipTurnDebuff(b) => Box::new(b),
+            BuffVariant::DamageReductionBuff(b) => Box::new(b),
+            BuffVariant::ShieldBuff(b) => Box::new(b),
+            BuffVariant::ImmunityBuff(b) => Box::new(b),
+            BuffVariant::ReflectBuff(b) => Box::new(b),
+            BuffVariant::DamageOverTimeDebuff(b) => Box::new(b),
+            BuffVariant::RegenerationBuff(b) => Box::new(b),
         }
     }
 }
@@ -111,6 +245,9 @@ impl BuffVariant {
 
 fn default_attack_op() -> EvalOp { EvalOp::Add }
 fn default_lifetime() -> BuffLifeTime { BuffLifeTime::UntilNextTurnEnd }
+/// defensive buffs protect the owner from the moment they are granted until the start of their next turn
+fn default_defense_lifetime() -> BuffLifeTime { BuffLifeTime::Turns { delay: 0, turns: 1 } }
+fn default_periodic_lifetime() -> BuffLifeTime { BuffLifeTime::Ticks { ticks: 3 } }
 
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -131,6 +268,7 @@ pub struct AttackBuff {
 
 impl Buff for AttackBuff {
     fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
     fn get_type(&self) -> BuffType {
         BuffType::Attack { value: self.value, op: self.op.clone(), elements: self.elements.clone(), kinds: self.kinds.clone(), stars: self.stars.clone() }
     }
@@ -143,10 +281,14 @@ impl Buff for AttackBuff {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 // #[serde(tag = "type")]
-// pub struct TargetAllBuff {}
-pub struct TargetAllBuff;
+pub struct TargetAllBuff {
+    #[serde(default = "default_lifetime")]
+    pub lifetime: BuffLifeTime,
+}
 
 impl Buff for TargetAllBuff {
+    fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
     fn get_type(&self) -> BuffType { BuffType::TargetAll }
 
     fn as_variant(&self) -> BuffVariant {
@@ -164,12 +306,164 @@ pub struct PlayAllCardsBuff {
     pub kinds: Vec<Kind>,
     #[serde(default)]
     pub stars: Vec<Stars>,
+    #[serde(default = "default_lifetime")]
+    pub lifetime: BuffLifeTime,
 }
 
 impl Buff for PlayAllCardsBuff {
+    fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
     fn get_type(&self) -> BuffType { BuffType::PlayAllCards { elements: self.elements.clone(), kinds: self.kinds.clone(), stars: self.stars.clone() } }
 
     fn as_variant(&self) -> BuffVariant {
         BuffVariant::PlayAllCardsBuff(self.clone())
     }
 }
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SkipTurnDebuff;
+
+impl Buff for SkipTurnDebuff {
+    fn get_type(&self) -> BuffType { BuffType::SkipTurn }
+
+    fn as_variant(&self) -> BuffVariant {
+        BuffVariant::SkipTurnDebuff(self.clone())
+    }
+}
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DamageReductionBuff {
+    pub value: u32,
+    /// if true, `value` is a percentage of the damage
+    #[serde(default)]
+    pub percent: bool,
+    #[serde(default = "default_defense_lifetime")]
+    pub lifetime: BuffLifeTime,
+}
+
+impl Buff for DamageReductionBuff {
+    fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
+    fn get_type(&self) -> BuffType { BuffType::DamageReduction { value: self.value, percent: self.percent } }
+
+    fn mitigate(&mut self, amount: u32) -> (u32, bool) {
+        if self.percent {
+            (amount - amount * self.value.min(100) / 100, false)
+        } else {
+            (amount.saturating_sub(self.value), false)
+        }
+    }
+
+    fn as_variant(&self) -> BuffVariant {
+        BuffVariant::DamageReductionBuff(self.clone())
+    }
+}
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ShieldBuff {
+    /// remaining points of the shield
+    pub points: u32,
+    #[serde(default = "default_defense_lifetime")]
+    pub lifetime: BuffLifeTime,
+}
+
+impl Buff for ShieldBuff {
+    fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
+    fn get_type(&self) -> BuffType { BuffType::Shield { points: self.points } }
+
+    fn mitigate(&mut self, amount: u32) -> (u32, bool) {
+        let absorbed = amount.min(self.points);
+        self.points -= absorbed;
+        (amount - absorbed, self.points == 0)
+    }
+
+    fn as_variant(&self) -> BuffVariant {
+        BuffVariant::ShieldBuff(self.clone())
+    }
+}
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImmunityBuff {
+    #[serde(default = "default_defense_lifetime")]
+    pub lifetime: BuffLifeTime,
+}
+
+impl Buff for ImmunityBuff {
+    fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
+    fn get_type(&self) -> BuffType { BuffType::Immunity }
+
+    fn mitigate(&mut self, amount: u32) -> (u32, bool) {
+        // a hit without damage does not use the immunity
+        (0, amount > 0)
+    }
+
+    fn as_variant(&self) -> BuffVariant {
+        BuffVariant::ImmunityBuff(self.clone())
+    }
+}
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReflectBuff {
+    pub percent: u32,
+    #[serde(default = "default_defense_lifetime")]
+    pub lifetime: BuffLifeTime,
+}
+
+impl Buff for ReflectBuff {
+    fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
+    fn get_type(&self) -> BuffType { BuffType::Reflect { percent: self.percent } }
+
+    fn as_variant(&self) -> BuffVariant {
+        BuffVariant::ReflectBuff(self.clone())
+    }
+}
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DamageOverTimeDebuff {
+    pub amount: u32,
+    #[serde(default)]
+    pub kind: DotKind,
+    #[serde(default)]
+    pub tick: TickTime,
+    #[serde(default = "default_periodic_lifetime")]
+    pub lifetime: BuffLifeTime,
+}
+
+impl Buff for DamageOverTimeDebuff {
+    fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
+    fn get_type(&self) -> BuffType { BuffType::DamageOverTime { amount: self.amount, kind: self.kind, tick: self.tick } }
+
+    fn as_variant(&self) -> BuffVariant {
+        BuffVariant::DamageOverTimeDebuff(self.clone())
+    }
+}
+
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RegenerationBuff {
+    pub amount: u32,
+    #[serde(default)]
+    pub tick: TickTime,
+    #[serde(default = "default_periodic_lifetime")]
+    pub lifetime: BuffLifeTime,
+}
+
+impl Buff for RegenerationBuff {
+    fn get_lifetime(&self) -> BuffLifeTime { self.lifetime.clone() }
+    fn set_lifetime(&mut self, lifetime: BuffLifeTime) { self.lifetime = lifetime; }
+    fn get_type(&self) -> BuffType { BuffType::Regeneration { amount: self.amount, tick: self.tick } }
+
+    fn as_variant(&self) -> BuffVariant {
+        BuffVariant::RegenerationBuff(self.clone())
+    }
+}
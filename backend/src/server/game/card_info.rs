@@ -1,16 +1,23 @@
 use std::fmt;
 
 use serde::{Deserializer, Serialize};
-use serde::{de::{SeqAccess, Visitor}, Deserialize};
+use serde::{de::{Error, SeqAccess, Visitor}, Deserialize};
 
 use super::modifiers::ModifierInfo;
-use super::buffs::BuffVariant;
+use super::buffs::{BuffVariant, Dispel};
 
-use super::cards::card::{BasicCard, Card, CardId, Element, Kind, Stars, TargetType};
+use super::cards::attack_or_heal_card::AttackOrHealCard;
+use super::cards::card::{BasicCard, Card, CardEffects, CardFilter, CardMove, DrawScope, CardId, Element, GameEffect, HandDiscard, Kind, NestedPlay, PlayCondition, Stars, TargetType};
+use super::cards::discard_choice_card::DiscardChoiceCard;
+use super::cards::discard_draw_card::{DiscardDrawCard, DiscardSource};
+use super::cards::effect_card::{EffectCard, EffectStep};
 use super::cards::multi_action_card::MultiActionCard;
 use super::cards::multi_hit_card::MultiHitCard;
 use super::cards::pearth_card::PearthCard;
+use super::cards::replay_card::{ReplayCard, MAX_REPLAYS};
+use super::cards::return_card::ReturnCard;
 use super::cards::players_rolls_dice_card::{PlayersRollsDiceCard, PlayersRollsDiceCardAction};
+use super::cards::steal_card::{CardTransfer, StealCard};
 use super::cards::target_both_card::TargetBothCard;
 
 
@@ -23,6 +30,13 @@ enum CardVariant {
     MultiActionCard(MultiActionCardData),
     PlayersRollsDiceCard(PlayersRollsDiceCardData),
     PearthCard,
+    StealCard(StealCardData),
+    DiscardDrawCard(DiscardDrawCardData),
+    ReplayCard(ReplayCardData),
+    ReturnCard(ReturnCardData),
+    DiscardChoiceCard(DiscardChoiceCardData),
+    EffectCard(EffectCardData),
+    AttackOrHealCard(AttackOrHealCardData),
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -92,6 +106,174 @@ struct PlayersRollsDiceCardData {
 }
 
 
+#[derive(Debug, Deserialize, Serialize)]
+struct StealCardData {
+    #[serde(default)]
+    attack: u32,
+    #[serde(default)]
+    heal: u32,
+    #[serde(default)]
+    draw: u32,
+    #[serde(default)]
+    attack_modifier: Option<ModifierInfo>,
+    #[serde(default)]
+    heal_modifier: Option<ModifierInfo>,
+    #[serde(default)]
+    draw_modifier: Option<ModifierInfo>,
+    #[serde(default)]
+    targets: TargetType,
+    #[serde(default)]
+    steal: Option<CardTransfer>,
+    #[serde(default)]
+    give: Option<CardTransfer>,
+}
+
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DiscardChoiceCardData {
+    attack_per_card: u32,
+    #[serde(default)]
+    targets: TargetType,
+}
+
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AttackOrHealCardData {
+    /// points removed from the targets or recovered by the player
+    #[serde(default)]
+    amount: u32,
+    #[serde(default)]
+    modifier: Option<ModifierInfo>,
+    #[serde(default)]
+    targets: TargetType,
+}
+
+
+#[derive(Debug, Deserialize, Serialize)]
+struct EffectCardData {
+    /// steps of the effect, run in order
+    steps: Vec<EffectStep>,
+    #[serde(default)]
+    targets: TargetType,
+}
+
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DiscardDrawCardData {
+    #[serde(default)]
+    attack: u32,
+    #[serde(default)]
+    heal: u32,
+    #[serde(default)]
+    draw: u32,
+    #[serde(default)]
+    attack_modifier: Option<ModifierInfo>,
+    #[serde(default)]
+    heal_modifier: Option<ModifierInfo>,
+    #[serde(default)]
+    draw_modifier: Option<ModifierInfo>,
+    #[serde(default)]
+    targets: TargetType,
+    discard_draw: DiscardDrawInfo,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DiscardDrawInfo {
+    #[serde(default)]
+    source: DiscardSource,
+    /// all matching cards are drawn if not set
+    #[serde(default)]
+    amount: Option<u32>,
+    #[serde(default)]
+    modifier: Option<ModifierInfo>,
+    #[serde(default)]
+    filter: CardFilter,
+}
+
+
+fn default_max_replays() -> u32 { MAX_REPLAYS }
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ReplayCardData {
+    /// card to replay, uses the common card data of the ReplayCard
+    card: Box<CardVariant>,
+    condition: PlayCondition,
+    #[serde(default)]
+    check_first: bool,
+    #[serde(default = "default_max_replays")]
+    max_replays: u32,
+}
+
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ReturnCardData {
+    /// card to play, uses the common card data of the ReturnCard
+    card: Box<CardVariant>,
+    /// the card always goes back to the hand if not set
+    #[serde(default)]
+    condition: Option<PlayCondition>,
+}
+
+
+/// Effects shared by the card variants, see CardEffects
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct CardEffectsData {
+    debuffs: Vec<BuffVariant>,
+    game_effects: Vec<GameEffect>,
+    discard: Option<HandDiscard>,
+    dispel: Option<Dispel>,
+    reveal_hand: bool,
+    nested_play: Option<NestedPlay>,
+    card_moves: Vec<CardMove>,
+    draw_scope: DrawScope,
+}
+
+impl CardEffectsData {
+    fn make_effects(&self) -> CardEffects {
+        CardEffects {
+            debuffs: self.debuffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+            game_effects: self.game_effects.clone(),
+            discard: self.discard.clone(),
+            dispel: self.dispel.clone(),
+            reveal_hand: self.reveal_hand,
+            nested_play: self.nested_play.clone(),
+            card_moves: self.card_moves.clone(),
+            draw_scope: self.draw_scope,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.debuffs.is_empty() && self.game_effects.is_empty() && self.discard.is_none() && self.dispel.is_none()
+            && !self.reveal_hand && self.nested_play.is_none() && self.card_moves.is_empty() && self.draw_scope == DrawScope::default()
+    }
+}
+
+impl CardVariant {
+    /// Check that the card variant applies the common effects set on the card
+    fn check_effects(&self, effects: &CardEffectsData) -> Result<(), String> {
+        match self {
+            CardVariant::BasicCard(_) | CardVariant::MultiHitCard(_) | CardVariant::StealCard(_) => Ok(()),
+            // the card draws for the player and the targets
+            CardVariant::TargetBothCard(_) if effects.draw_scope != DrawScope::default() => Err("draw_scope is not supported by TargetBothCard".to_string()),
+            // the card discards the cards chosen by the player
+            CardVariant::DiscardChoiceCard(_) if effects.discard.is_some() => Err("discard is not supported by DiscardChoiceCard".to_string()),
+            // the choice of the card is not the card to swap
+            CardVariant::DiscardChoiceCard(_) | CardVariant::DiscardDrawCard(_) | CardVariant::AttackOrHealCard(_) if effects.card_moves.contains(&CardMove::SwapWithTargetDiscard) => {
+                Err("SwapWithTargetDiscard is not supported by cards with their own choice".to_string())
+            }
+            CardVariant::TargetBothCard(_) | CardVariant::DiscardChoiceCard(_) | CardVariant::DiscardDrawCard(_) | CardVariant::AttackOrHealCard(_) => Ok(()),
+            // the effects are applied by the replayed card
+            CardVariant::ReplayCard(data) => data.card.check_effects(effects),
+            CardVariant::ReturnCard(data) => data.card.check_effects(effects),
+            CardVariant::MultiActionCard(_) | CardVariant::PlayersRollsDiceCard(_) | CardVariant::PearthCard | CardVariant::EffectCard(_) => {
+                if effects.is_empty() { Ok(()) } else { Err("common card effects are not supported by this card type".to_string()) }
+            }
+        }
+    }
+}
+
+
 /// Common card data
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CardInfo {
@@ -106,12 +288,18 @@ pub struct CardInfo {
     #[serde(default)]
     buffs: Vec<BuffVariant>,
     #[serde(flatten)]
+    effects: CardEffectsData,
+    #[serde(flatten)]
     variant: CardVariant
 }
 
 impl CardInfo {
     pub fn make_card(&self) -> Box<dyn Card> {
-        match &self.variant {
+        self.make_card_variant(&self.variant)
+    }
+
+    fn make_card_variant(&self, variant: &CardVariant) -> Box<dyn Card> {
+        match variant {
             CardVariant::BasicCard(data) => {
                 Box::new(BasicCard {
                     id: self.id,
@@ -128,6 +316,7 @@ impl CardInfo {
                     draw_modifier: data.draw_modifier.clone().map(|m| m.into_boxed()),
                     target_type: data.targets,
                     buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                    effects: self.effects.make_effects(),
                 })
             },
             CardVariant::MultiHitCard(data) => {
@@ -144,6 +333,7 @@ impl CardInfo {
                     heal_modifier: data.heal_modifier.clone().map(|m| m.into_boxed()),
                     draw_modifier: data.draw_modifier.clone().map(|m| m.into_boxed()),
                     buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                    effects: self.effects.make_effects(),
                 })
             },
             CardVariant::TargetBothCard(data) => {
@@ -162,6 +352,7 @@ impl CardInfo {
                     draw_modifier: data.draw_modifier.clone().map(|m| m.into_boxed()),
                     target_type: data.targets,
                     buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                    effects: self.effects.make_effects(),
                 })
             }
             CardVariant::MultiActionCard(data) => {
@@ -209,6 +400,108 @@ impl CardInfo {
                     desc: self.desc.clone(),
                 })
             }
+            CardVariant::StealCard(data) => {
+                Box::new(StealCard {
+                    id: self.id,
+                    name: self.name.clone(),
+                    element: self.element,
+                    stars: self.stars,
+                    kind: self.kind,
+                    desc: self.desc.clone(),
+                    attack: data.attack,
+                    heal: data.heal,
+                    draw: data.draw,
+                    attack_modifier: data.attack_modifier.clone().map(|m| m.into_boxed()),
+                    heal_modifier: data.heal_modifier.clone().map(|m| m.into_boxed()),
+                    draw_modifier: data.draw_modifier.clone().map(|m| m.into_boxed()),
+                    target_type: data.targets,
+                    steal: data.steal.clone(),
+                    give: data.give.clone(),
+                    buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                    effects: self.effects.make_effects(),
+                })
+            }
+            CardVariant::DiscardDrawCard(data) => {
+                Box::new(DiscardDrawCard {
+                    id: self.id,
+                    name: self.name.clone(),
+                    element: self.element,
+                    stars: self.stars,
+                    kind: self.kind,
+                    desc: self.desc.clone(),
+                    attack: data.attack,
+                    heal: data.heal,
+                    draw: data.draw,
+                    attack_modifier: data.attack_modifier.clone().map(|m| m.into_boxed()),
+                    heal_modifier: data.heal_modifier.clone().map(|m| m.into_boxed()),
+                    draw_modifier: data.draw_modifier.clone().map(|m| m.into_boxed()),
+                    target_type: data.targets,
+                    discard_source: data.discard_draw.source,
+                    discard_draw: data.discard_draw.amount,
+                    discard_draw_modifier: data.discard_draw.modifier.clone().map(|m| m.into_boxed()),
+                    discard_filter: data.discard_draw.filter.clone(),
+                    buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                    effects: self.effects.make_effects(),
+                })
+            }
+            CardVariant::ReplayCard(data) => {
+                Box::new(ReplayCard {
+                    card: self.make_card_variant(&data.card),
+                    condition: data.condition.clone(),
+                    check_first: data.check_first,
+                    max_replays: data.max_replays,
+                    buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                })
+            }
+            CardVariant::ReturnCard(data) => {
+                Box::new(ReturnCard {
+                    card: self.make_card_variant(&data.card),
+                    condition: data.condition.clone(),
+                    buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                })
+            }
+            CardVariant::DiscardChoiceCard(data) => {
+                Box::new(DiscardChoiceCard {
+                    id: self.id,
+                    name: self.name.clone(),
+                    element: self.element,
+                    stars: self.stars,
+                    kind: self.kind,
+                    desc: self.desc.clone(),
+                    target_type: data.targets,
+                    attack_per_card: data.attack_per_card,
+                    buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                    effects: self.effects.make_effects(),
+                })
+            }
+            CardVariant::AttackOrHealCard(data) => {
+                Box::new(AttackOrHealCard {
+                    id: self.id,
+                    name: self.name.clone(),
+                    element: self.element,
+                    stars: self.stars,
+                    kind: self.kind,
+                    desc: self.desc.clone(),
+                    target_type: data.targets,
+                    amount: data.amount,
+                    modifier: data.modifier.clone().map(|m| m.into_boxed()),
+                    buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                    effects: self.effects.make_effects(),
+                })
+            }
+            CardVariant::EffectCard(data) => {
+                Box::new(EffectCard {
+                    id: self.id,
+                    name: self.name.clone(),
+                    element: self.element,
+                    stars: self.stars,
+                    kind: self.kind,
+                    desc: self.desc.clone(),
+                    target_type: data.targets,
+                    steps: data.steps.clone(),
+                    buffs: self.buffs.clone().into_iter().map(|b| b.into_boxed()).collect(),
+                })
+            }
         }
     }
 }
@@ -238,6 +531,8 @@ impl<'de> Deserialize<'de> for CardInfoList {
                 let mut idx = 0;
 
                 while let Some(mut p) = seq.next_element::<CardInfo>()? {
+                    p.variant.check_effects(&p.effects)
+                        .map_err(|msg| A::Error::custom(format!("{}: {}", p.name, msg)))?;
                     p.id = idx;
                     idx += 1;
                     card_info_list.push(p);
@@ -250,3 +545,35 @@ impl<'de> Deserialize<'de> for CardInfoList {
         deserializer.deserialize_seq(CardInfoVisitor)
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(cards: &str) -> Result<CardInfoList, serde_json::Error> {
+        serde_json::from_str::<CardInfoList>(cards)
+    }
+
+    fn is_unsupported(cards: &str) -> bool {
+        parse(cards).is_err_and(|e| e.to_string().contains("not supported"))
+    }
+
+    #[test]
+    fn common_effects_are_applied() {
+        let list = parse(r#"[{"name":"Test","element":"Water","stars":"One","kind":"Spell","type":"TargetBothCard",
+            "attack":1,"reveal_hand":true,"debuffs":[{"type":"SkipTurnDebuff"}]}]"#).unwrap();
+        let card = list.0[0].make_card();
+
+        assert!(card.get_reveal_hand());
+        assert_eq!(card.get_debuffs().len(), 1);
+    }
+
+    #[test]
+    fn unsupported_effects_are_rejected() {
+        assert!(is_unsupported(r#"[{"name":"Test","element":"Water","stars":"One","kind":"Spell","type":"PearthCard","reveal_hand":true}]"#));
+        assert!(is_unsupported(r#"[{"name":"Test","element":"Water","stars":"One","kind":"Spell","type":"TargetBothCard","draw":1,"draw_scope":"All"}]"#));
+        assert!(is_unsupported(r#"[{"name":"Test","element":"Water","stars":"One","kind":"Spell","type":"ReplayCard",
+            "card":{"type":"DiscardChoiceCard","attack_per_card":1},"condition":{"type":"EvenDiceRoll"},"discard":{}}]"#));
+    }
+}
@@ -1,6 +1,13 @@
+pub mod attack_or_heal_card;
 pub mod card;
+pub mod discard_choice_card;
+pub mod discard_draw_card;
+pub mod effect_card;
 pub mod multi_action_card;
 pub mod multi_hit_card;
 pub mod pearth_card;
+pub mod replay_card;
+pub mod return_card;
 pub mod players_rolls_dice_card;
+pub mod steal_card;
 pub mod target_both_card;
@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+
+use super::card::{check_apply_attack_buffs, Card, CardEffects, CardId, Element, Kind, Stars, TargetType};
+use super::super::modifiers::Modifier;
+use super::super::buffs::Buff;
+use super::super::choice::{Choice, ChoiceRequest};
+use super::super::game::Game;
+use super::super::play_info::{PlayAction, PlayInfo};
+
+
+/// Card variant that lets the player choose to remove `amount` points from the targets or to recover them.
+/// The targets are attacked if the player did not choose.
+#[derive(Debug, Clone)]
+pub struct AttackOrHealCard {
+    pub id: CardId,
+    pub name: String,
+    pub element: Element,
+    pub stars: Stars,
+    pub kind: Kind,
+    pub desc: String,
+    pub target_type: TargetType,
+    pub amount: u32,
+    pub modifier: Option<Box<dyn Modifier>>,
+    pub buffs: Vec<Box<dyn Buff>>,
+    pub effects: CardEffects,
+}
+
+impl AttackOrHealCard {
+    /// True if the player chose to recover the points
+    fn is_heal_chosen(&self, game: &Game) -> bool {
+        matches!(game.choice, Some(Choice::AttackOrHeal { heal: true }))
+    }
+}
+
+impl Card for AttackOrHealCard {
+    fn get_id(&self) -> CardId { self.id }
+    fn get_name(&self) -> String { String::from(&self.name) }
+    fn get_attack(&self) -> u32 { self.amount }
+    fn get_attack_modifier(&self) -> Option<Box<dyn Modifier>> { self.modifier.clone() }
+    fn get_heal(&self) -> u32 { self.amount }
+    fn get_heal_modifier(&self) -> Option<Box<dyn Modifier>> { self.modifier.clone() }
+    fn get_description(&self) -> String { String::from(&self.desc) }
+    fn get_kind(&self) -> Kind { self.kind }
+    fn get_element(&self) -> Element { self.element }
+    fn get_stars(&self) -> Stars { self.stars }
+    fn get_target_type(&self) -> TargetType { self.target_type }
+    fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { Some(&self.effects) }
+
+    fn get_choice_request(&self, _player_index: usize, _card_index: usize, _target_indices: &Vec<usize>, _game: &Game) -> Option<(ChoiceRequest, Choice)> {
+        Some((ChoiceRequest::AttackOrHeal, Choice::AttackOrHeal { heal: false }))
+    }
+
+    fn handle_attack(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, dice_roll: u8, dice_roll_used: &mut bool, buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        if self.is_heal_chosen(game) {
+            return Ok(());
+        }
+
+        for &target_index in target_indices {
+            let mut attack_action: PlayAction = PlayAction::new();
+            let affinity = game.get_affinity(self.get_element(), target_index);
+
+            // use split_at_mut() to prevent warnings about mutable borrows
+            let (player, target) = if player_index < target_index {
+                let (left, right) = game.players.split_at_mut(target_index);
+                (&mut left[player_index], &mut right[0])
+            } else if player_index > target_index {
+                let (left, right) = game.players.split_at_mut(player_index);
+                (&mut right[0], &mut left[target_index])
+            } else {
+                return Err("Target is player !".to_string());
+            };
+
+            let (amount, dice_rolls, player_dice_id) = {
+                if let Some(modifier) = &self.modifier {
+                    modifier.compute_rolls(self.amount, player, target, Some(dice_roll), &mut game.rng)
+                } else { (self.amount, Vec::new(), -1) }
+            };
+
+            // show dice anim on client only if this is the first time the dice is used
+            attack_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
+
+            // apply attack buffs
+            let amount = check_apply_attack_buffs(amount, &player.buffs, &game.play_context, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
+
+            let action_target = target.damage(amount, affinity, self.get_damage_effect());
+            attack_action.targets.push(action_target);
+            info.actions.push(attack_action);
+        }
+
+        Ok(())
+    }
+
+    fn handle_heal(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, _target_indices: &Vec<usize>, dice_roll: u8, dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        if !self.is_heal_chosen(game) {
+            return Ok(());
+        }
+
+        let mut heal_action: PlayAction = PlayAction::new();
+        let player = &mut game.players[player_index];
+
+        let (amount, dice_rolls, player_dice_id) = {
+            if let Some(modifier) = &self.modifier {
+                modifier.compute_rolls(self.amount, player, player, Some(dice_roll), &mut game.rng)
+            } else { (self.amount, Vec::new(), -1) }
+        };
+
+        // show dice anim on client only if this is the first time the dice is used
+        heal_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
+
+        let action_target = player.heal(amount, self.get_heal_effect());
+        heal_action.targets.push(action_target);
+        info.actions.push(heal_action);
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::server::game::card_info::CardInfo;
+    use crate::server::game::choice::Choice;
+    use crate::server::game::game::test_utils::make_game;
+
+    const CARD: &str = r#"{"name":"Test","element":"Water","stars":"One","kind":"Spell","type":"AttackOrHealCard","amount":5}"#;
+
+    #[test]
+    fn the_player_chooses_attack_or_heal() {
+        let mut game = make_game(2);
+        game.players[0].health -= 10;
+        let (health, target_health) = (game.players[0].health, game.players[1].health);
+
+        game.players[0].hand_cards.push(serde_json::from_str::<CardInfo>(CARD).unwrap().make_card());
+        game.play_card(1, 0, vec![2]).unwrap();
+        assert!(game.pending_choice.is_some());
+        game.resolve_choice(1, Some(Choice::AttackOrHeal { heal: true })).unwrap();
+        assert_eq!((game.players[0].health, game.players[1].health), (health + 5, target_health));
+
+        game.players[0].hand_cards.push(serde_json::from_str::<CardInfo>(CARD).unwrap().make_card());
+        game.play_card(1, 0, vec![2]).unwrap();
+        game.resolve_choice(1, None).unwrap();
+        assert_eq!((game.players[0].health, game.players[1].health), (health + 5, target_health - 5));
+    }
+}
@@ -1,21 +1,23 @@
 use std::collections::HashSet;
 use std::fmt::{self, Debug, Display};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use crate::utils::clamp::clamp;
 
-use super::super::game::{Game, MAX_PLAYERS};
+use super::super::game::{Game, GameRng, Neighbour, Order, PlayContext, MAX_PLAYERS};
 use super::super::play_info::{PlayAction, PlayInfo, ActionTarget, ActionType};
 use super::super::modifiers::Modifier;
-use super::super::buffs::{Buff, BuffType};
-use super::super::player::Player;
+use super::super::buffs::{Buff, BuffLifeTime, BuffType, Dispel};
+use super::super::player::{Player, PlayerId};
+use super::super::choice::{Choice, ChoiceRequest};
 
 
 // TODO define effects
 pub type EffectId = String;
 
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum Element {
     Fire,
     Air,
@@ -70,6 +72,216 @@ pub enum TargetType {
 pub type CardId = i32;
 
 
+/// Filter used to select cards by element, kind or stars.
+/// If no elements, kinds or stars defined, it behaves as if all variants are valid.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub struct CardFilter {
+    #[serde(default)]
+    pub elements: Vec<Element>,
+    #[serde(default)]
+    pub kinds: Vec<Kind>,
+    #[serde(default)]
+    pub stars: Vec<Stars>,
+}
+
+impl CardFilter {
+    pub fn is_matching(&self, card: &dyn Card) -> bool {
+        // no elements, kinds or stars defined -> ok (all)
+        (self.elements.is_empty() || self.elements.iter().any(|&e| e == card.get_element()))
+        && (self.kinds.is_empty() || self.kinds.iter().any(|&k| k == card.get_kind()))
+        && (self.stars.is_empty() || self.stars.iter().any(|&s| s == card.get_stars()))
+    }
+}
+
+
+/// Players drawing the cards of a card
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub enum DrawScope {
+    #[default]
+    Caster,
+    Targets,
+    /// all players in turn order, starting with the caster
+    All,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub enum DiscardScope {
+    #[default]
+    Targets,
+    Caster,
+    /// all players, including the caster
+    All,
+}
+
+/// Cards discarded from the hands of players when a card is played
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub struct HandDiscard {
+    #[serde(default)]
+    pub scope: DiscardScope,
+    #[serde(default)]
+    pub filter: CardFilter,
+    /// if true, the cards that don't match the filter are discarded instead
+    #[serde(default)]
+    pub exclude: bool,
+}
+
+
+/// Effect of a card on the state of the game
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum GameEffect {
+    ReverseTurnOrder,
+    SetTurnOrder { order: Order },
+}
+
+impl GameEffect {
+    pub fn apply(&self, game: &mut Game) {
+        match self {
+            GameEffect::ReverseTurnOrder => game.reverse_turn_order(),
+            GameEffect::SetTurnOrder { order } => game.turn_order = *order,
+        }
+    }
+}
+
+
+/// Cards moved between the hands and discard piles of players when a card is played
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum CardMove {
+    /// the played card is given to a neighbour of the player instead of being discarded
+    GiveToNeighbour { neighbour: Neighbour },
+    /// the discard piles of the targets are put on top of the discard pile of the player
+    TakeDiscardPiles,
+    /// a card of the hand (chosen by the player) is exchanged with the last card of the discard pile of the first target
+    SwapWithTargetDiscard,
+}
+
+
+/// Where the card played by a nested play comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub enum NestedPlaySource {
+    /// first card of the pile
+    #[default]
+    Pile,
+    /// last card of the discard pile of the first target
+    TargetDiscard,
+}
+
+fn default_attack_mul() -> u32 { 1 }
+
+/// Card drawn by the player and played on the same targets when a card is played
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub struct NestedPlay {
+    #[serde(default)]
+    pub source: NestedPlaySource,
+    /// the drawn card is only played if it matches the filter, else it stays in the hand of the player
+    #[serde(default)]
+    pub filter: CardFilter,
+    /// multiplier applied to the attack of the played card
+    #[serde(default = "default_attack_mul")]
+    pub attack_mul: u32,
+}
+
+
+/// Effects shared by the card variants that use the play() impl from Card trait
+#[derive(Debug, Clone, Default)]
+pub struct CardEffects {
+    pub debuffs: Vec<Box<dyn Buff>>,
+    pub game_effects: Vec<GameEffect>,
+    pub discard: Option<HandDiscard>,
+    pub dispel: Option<Dispel>,
+    pub reveal_hand: bool,
+    pub nested_play: Option<NestedPlay>,
+    pub card_moves: Vec<CardMove>,
+    pub draw_scope: DrawScope,
+}
+
+
+/// Condition checked on the actions made when playing a card
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum PlayCondition {
+    /// the dice roll is one of the values
+    DiceRoll { values: Vec<u8> },
+    /// the dice roll is an even number
+    EvenDiceRoll,
+    /// a card matching the filter was drawn or stolen by the player
+    CardReceived { filter: CardFilter },
+}
+
+impl PlayCondition {
+    pub fn is_dice_roll_valid(&self, dice_roll: u8) -> bool {
+        match self {
+            PlayCondition::DiceRoll { values } => values.contains(&dice_roll),
+            PlayCondition::EvenDiceRoll => dice_roll.is_multiple_of(2),
+            PlayCondition::CardReceived { .. } => false,
+        }
+    }
+
+    /// Check the condition on the actions of `info` starting at `first_action`.
+    /// If the condition needs a dice roll and the player did not roll the dice in these actions,
+    /// the dice is rolled and the roll is added to `info`.
+    pub fn check(&self, info: &mut PlayInfo, first_action: usize, player: &Player, rng: &mut GameRng) -> bool {
+        match self {
+            PlayCondition::DiceRoll { .. } | PlayCondition::EvenDiceRoll => {
+                let dice_roll = info.actions[first_action..].iter()
+                    .find(|action| action.dice_roll > 0 && action.player_dice_id == player.id)
+                    .map(|action| action.dice_roll);
+
+                let dice_roll = dice_roll.unwrap_or_else(|| roll_dice(info, rng, player.id));
+                self.is_dice_roll_valid(dice_roll)
+            }
+            PlayCondition::CardReceived { filter } => {
+                info.actions[first_action..].iter()
+                    .flat_map(|action| action.targets.iter())
+                    .flat_map(|target| match &target.action {
+                        ActionType::Draw { cards } | ActionType::DrawDiscard { cards, .. } if target.player_id == player.id => cards.clone(),
+                        ActionType::Steal { to, cards } if *to == player.id => cards.clone(),
+                        _ => Vec::new()
+                    })
+                    .filter_map(|card_id| player.hand_cards.iter().find(|c| c.get_id() == card_id))
+                    .any(|card| filter.is_matching(card.as_ref()))
+            }
+        }
+    }
+}
+
+/// Collect the discard piles in the pile if it has less than `amount` cards,
+/// and add an action to show it on the client (`player_index` is the player that needs to draw)
+pub fn refill_pile(info: &mut PlayInfo, game: &mut Game, player_index: usize, amount: u32) {
+    if game.refill_pile(amount as usize) {
+        let mut collect_action = PlayAction::new();
+        collect_action.targets.push(ActionTarget {
+            player_id: game.players[player_index].id,
+            action: ActionType::CollectDiscardCards { cards_in_pile: game.pile.len() as u32 },
+            effect: String::new()
+        });
+        info.actions.push(collect_action);
+    }
+}
+
+/// Draw cards from the pile for a player, the discard piles are collected first if the pile does not have enough cards
+pub fn draw_cards(info: &mut PlayInfo, game: &mut Game, player_index: usize, amount: u32) -> Vec<CardId> {
+    refill_pile(info, game, player_index, amount);
+    Game::give_from_pile(&mut game.pile, &mut game.players[player_index], amount as usize)
+}
+
+/// Roll the dice for the player and add an action to show the roll on the client
+pub fn roll_dice(info: &mut PlayInfo, rng: &mut GameRng, player_id: PlayerId) -> u8 {
+    let dice_roll = rng.random_range(0..6) + 1;
+
+    let mut dice_roll_action = PlayAction::new();
+    dice_roll_action.dice_roll = dice_roll;
+    dice_roll_action.player_dice_id = player_id;
+    info.actions.push(dice_roll_action);
+
+    dice_roll
+}
+
+
+// the handle_* methods all take the state of the play, with the targets of the card
+#[allow(clippy::too_many_arguments, clippy::ptr_arg)]
 pub trait Card: Sync + Send + Debug + CardClone {
 
     // common play impl
@@ -84,17 +296,30 @@ pub trait Card: Sync + Send + Debug + CardClone {
                 let mut info: PlayInfo = PlayInfo::new();
                 
                 let target_indices = {
-                    if self.get_target_type() == TargetType::All || game.players[player_index].buffs.iter().any(|b| b.get_type() == BuffType::TargetAll) {
+                    if self.get_target_type() == TargetType::All || game.players[player_index].buffs.iter().any(|b| b.is_active() && b.get_type() == BuffType::TargetAll) {
                         game.players.iter().enumerate().filter(|(i, _)| *i != player_index).map(|(i, _)| i).collect() 
                     } else { target_indices }
                 };
 
-                let dice_roll = rand::random_range(0..6) + 1;   // dice roll value to give to modifiers
+                let dice_roll = game.roll_card_dice();   // dice roll value to give to modifiers
                 let mut dice_roll_used = false;
 
+                // discard first for attacks depending on the discard pile
+                self.handle_discard(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
                 self.handle_attack(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
                 self.handle_heal(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
                 self.handle_draw(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                self.handle_discard_draw(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                self.handle_steal(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                self.handle_give(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                // dispel before giving debuffs so the debuffs of the card are kept
+                self.handle_dispel(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                self.handle_debuffs(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                self.handle_game_effects(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                self.handle_reveal_hand(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                self.handle_card_moves(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
+                // last, the nested card uses the state left by this card
+                self.handle_nested_play(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, &mut buffs_used)?;
 
                 Ok((info, buffs_used))
             }
@@ -107,6 +332,7 @@ pub trait Card: Sync + Send + Debug + CardClone {
         if self.get_attack() > 0 || self.get_attack_modifier().is_some() {
             for &target_index in target_indices {
                 let mut attack_action: PlayAction = PlayAction::new();
+                let affinity = game.get_affinity(self.get_element(), target_index);
 
                 // use split_at_mut() to prevent warnings about mutable borrows
                 let (player, target) = if player_index < target_index {
@@ -119,23 +345,19 @@ pub trait Card: Sync + Send + Debug + CardClone {
                     return Err("Target is player !".to_string());
                 };
                 
-                let (amount, dice_roll, player_dice_id) = {
+                let (amount, dice_rolls, player_dice_id) = {
                     if let Some(modifier) = self.get_attack_modifier() {
-                        modifier.compute(self.get_attack(), player, target, Some(dice_roll))
-                    } else { (self.get_attack(), 0, -1) }
+                        modifier.compute_rolls(self.get_attack(), player, target, Some(dice_roll), &mut game.rng)
+                    } else { (self.get_attack(), Vec::new(), -1) }
                 };
 
                 // show dice anim on client only if this is the first time the dice is used
-                if !*dice_roll_used && player_dice_id != -1 {
-                    attack_action.dice_roll = dice_roll;
-                    attack_action.player_dice_id = player_dice_id;
-                    *dice_roll_used = true;
-                }
+                attack_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
                 // apply attack buffs
-                let amount = check_apply_attack_buffs(amount, &player.buffs, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
+                let amount = check_apply_attack_buffs(amount, &player.buffs, &game.play_context, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
 
-                let action_target = target.damage(amount, self.get_damage_effect());
+                let action_target = target.damage(amount, affinity, self.get_damage_effect());
                 attack_action.targets.push(action_target);
                 info.actions.push(attack_action);
             }
@@ -151,18 +373,14 @@ pub trait Card: Sync + Send + Debug + CardClone {
         if self.get_heal() > 0 || self.get_heal_modifier().is_some() {
             let mut heal_action: PlayAction = PlayAction::new();
 
-            let (amount, dice_roll, player_dice_id) = {
+            let (amount, dice_rolls, player_dice_id) = {
                 if let Some(modifier) = self.get_heal_modifier() {
-                    modifier.compute(self.get_heal(), player, player, Some(dice_roll))
-                } else { (self.get_heal(), 0, -1) }
+                    modifier.compute_rolls(self.get_heal(), player, player, Some(dice_roll), &mut game.rng)
+                } else { (self.get_heal(), Vec::new(), -1) }
             };
 
             // show dice anim on client only if this is the first time the dice is used
-            if !*dice_roll_used && player_dice_id != -1 {
-                heal_action.dice_roll = dice_roll;
-                heal_action.player_dice_id = player_dice_id;
-                *dice_roll_used = true;
-            }
+            heal_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
             let action_target = player.heal(amount, self.get_heal_effect());
             heal_action.targets.push(action_target);
@@ -173,36 +391,287 @@ pub trait Card: Sync + Send + Debug + CardClone {
     }
 
     // basic draw impl, draw cards for current player
-    fn handle_draw(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, _target_indices: &Vec<usize>, dice_roll: u8, dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
-        let player = &mut game.players[player_index];
-
+    fn handle_draw(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, dice_roll: u8, dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
         if self.get_draw() > 0 || self.get_draw_modifier().is_some() {
-            // FIXME handle discard cards collection later, for now we can't draw more cards than there is in pile
+            let player_indices = match self.get_draw_scope() {
+                DrawScope::Caster => vec![player_index],
+                DrawScope::Targets => target_indices.iter()
+                    .copied()
+                    .filter(|&i| game.players[i].health > 0)
+                    .collect(),
+                DrawScope::All => game.players_in_turn_order(player_index).into_iter()
+                    .filter(|&i| game.players[i].health > 0)
+                    .collect(),
+            };
 
-            let (amount, dice_roll, player_dice_id) = {
-                if let Some(modifier) = self.get_draw_modifier() {
-                    modifier.compute(self.get_draw(), player, player, Some(dice_roll))
-                } else { (self.get_draw(), 0, -1) }
+            for i in player_indices {
+                let (amount, dice_rolls, player_dice_id) = {
+                    let player = &game.players[i];
+                    if let Some(modifier) = self.get_draw_modifier() {
+                        modifier.compute_rolls(self.get_draw(), player, player, Some(dice_roll), &mut game.rng)
+                    } else { (self.get_draw(), Vec::new(), -1) }
+                };
+
+                let drawn_cards = draw_cards(info, game, i, amount);
+                if !drawn_cards.is_empty() {
+                    let mut draw_action = PlayAction::new();
+
+                    // show dice anim on client only if this is the first time the dice is used
+                    draw_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
+
+                    draw_action.targets.push(ActionTarget {
+                        player_id: game.players[i].id,
+                        action: ActionType::Draw { cards: drawn_cards },
+                        effect: String::new()
+                    });
+                    info.actions.push(draw_action);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_discard(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        if let Some(discard) = self.get_discard() {
+            let player_indices: Vec<usize> = match discard.scope {
+                DiscardScope::Targets => target_indices.iter()
+                    .copied()
+                    .filter(|&i| game.players[i].health > 0)
+                    .collect(),
+                DiscardScope::Caster => vec![player_index],
+                DiscardScope::All => (0..game.players.len())
+                    .filter(|&i| game.players[i].health > 0)
+                    .collect(),
             };
 
-            let drawn_cards = Game::give_from_pile(&mut game.pile, player, amount as usize);
-            if drawn_cards.len() > 0 {
-                let mut draw_action = PlayAction::new();
-                
-                // show dice anim on client only if this is the first time the dice is used
-                if !*dice_roll_used && player_dice_id != -1 {
-                    draw_action.dice_roll = dice_roll;
-                    draw_action.player_dice_id = player_dice_id;
-                    *dice_roll_used = true;
+            let mut discard_action = PlayAction::new();
+            for i in player_indices {
+                let player = &mut game.players[i];
+                // the played card is still in hand, it must not be discarded
+                let discarded = player.discard_hand_cards(&discard.filter, discard.exclude, None, Some(self.get_id()), &mut game.rng);
+                if !discarded.is_empty() {
+                    discard_action.targets.push(ActionTarget {
+                        player_id: player.id,
+                        action: ActionType::Discard { cards: discarded },
+                        effect: String::new()
+                    });
                 }
+            }
+
+            if !discard_action.targets.is_empty() {
+                info.actions.push(discard_action);
+            }
+        }
+
+        Ok(())
+    }
 
-                draw_action.targets.push(ActionTarget {
-                    player_id: player.id,
-                    action: ActionType::Draw { cards: drawn_cards },    // FIXME set to -1 when sending to clients that aren't the current player
+    // no draw from discard piles by default
+    fn handle_discard_draw(&self, _info: &mut PlayInfo, _game: &mut Game, _player_index: usize, _target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        Ok(())
+    }
+
+    // no steal by default
+    fn handle_steal(&self, _info: &mut PlayInfo, _game: &mut Game, _player_index: usize, _target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        Ok(())
+    }
+
+    // no give by default
+    fn handle_give(&self, _info: &mut PlayInfo, _game: &mut Game, _player_index: usize, _target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn handle_dispel(&self, info: &mut PlayInfo, game: &mut Game, _player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        if let Some(dispel) = self.get_dispel() {
+            let mut dispel_action = PlayAction::new();
+
+            for &i in target_indices.iter() {
+                let target = &mut game.players[i];
+                let removed = target.dispel_buffs(&dispel);
+                if !removed.is_empty() {
+                    dispel_action.targets.push(ActionTarget {
+                        player_id: target.id,
+                        action: ActionType::Dispel { buffs: removed.iter().map(|b| b.as_variant()).collect() },
+                        effect: String::new()
+                    });
+                }
+            }
+
+            if !dispel_action.targets.is_empty() {
+                info.actions.push(dispel_action);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_debuffs(&self, info: &mut PlayInfo, game: &mut Game, _player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        let debuffs = self.get_debuffs();
+
+        if !debuffs.is_empty() && !target_indices.is_empty() {
+            let mut debuff_action = PlayAction::new();
+
+            for &i in target_indices.iter() {
+                let target = &mut game.players[i];
+                for debuff in debuffs.iter() {
+                    target.buffs.push(debuff.clone());
+                    debuff_action.targets.push(ActionTarget {
+                        player_id: target.id,
+                        action: ActionType::Debuff { buff: debuff.as_variant() },
+                        effect: String::new()
+                    });
+                }
+            }
+            info.actions.push(debuff_action);
+        }
+
+        Ok(())
+    }
+
+    fn handle_reveal_hand(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        if self.get_reveal_hand() && !target_indices.is_empty() {
+            let mut reveal_action = PlayAction::new();
+
+            for &i in target_indices.iter() {
+                let target = &game.players[i];
+                reveal_action.targets.push(ActionTarget {
+                    player_id: target.id,
+                    action: ActionType::Reveal {
+                        to: game.players[player_index].id,
+                        cards: target.hand_cards.iter().map(|c| c.get_id()).collect()
+                    },
                     effect: String::new()
                 });
-                info.actions.push(draw_action);
             }
+            info.actions.push(reveal_action);
+        }
+
+        Ok(())
+    }
+
+    fn handle_card_moves(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        let player_id = game.players[player_index].id;
+
+        for card_move in self.get_card_moves() {
+            let mut move_action = PlayAction::new();
+
+            match card_move {
+                CardMove::GiveToNeighbour { neighbour } => {
+                    let receiver_id = game.players[game.neighbour_index(player_index, neighbour)].id;
+                    if receiver_id != player_id {
+                        info.give_to = Some(receiver_id);
+                        move_action.targets.push(ActionTarget {
+                            player_id,
+                            action: ActionType::PassCard { to: receiver_id, card: self.get_id() },
+                            effect: String::new()
+                        });
+                    }
+                }
+                CardMove::TakeDiscardPiles => {
+                    for &i in target_indices.iter() {
+                        let cards = game.move_discard_pile(i, player_index);
+                        if !cards.is_empty() {
+                            move_action.targets.push(ActionTarget {
+                                player_id: game.players[i].id,
+                                action: ActionType::MoveDiscardPile { to: player_id, cards },
+                                effect: String::new()
+                            });
+                        }
+                    }
+                }
+                CardMove::SwapWithTargetDiscard => {
+                    let hand_index = match &game.choice {
+                        Some(Choice::HandCards { cards }) => cards.first().cloned(),
+                        _ => None
+                    };
+
+                    if let (Some(hand_index), Some(&target_index)) = (hand_index, target_indices.first()) {
+                        if let Some((given, taken)) = game.swap_hand_with_discard(player_index, hand_index, target_index) {
+                            move_action.targets.push(ActionTarget {
+                                player_id,
+                                action: ActionType::SwapWithDiscard { with: game.players[target_index].id, given, taken },
+                                effect: String::new()
+                            });
+                        }
+                    }
+                }
+            }
+
+            if !move_action.targets.is_empty() {
+                info.actions.push(move_action);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_nested_play(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        if let Some(nested_play) = self.get_nested_play() {
+            // no card is played if the game is over
+            if game.players.iter().filter(|p| p.health > 0).count() <= 1 {
+                return Ok(());
+            }
+
+            let player_id = game.players[player_index].id;
+            let (card, action) = match nested_play.source {
+                NestedPlaySource::Pile => {
+                    // drawn like the other cards, the discard piles are collected if the pile is empty
+                    refill_pile(info, game, player_index, 1);
+                    match game.pile.first() {
+                        Some(card) if nested_play.filter.is_matching(card.as_ref()) => {
+                            let card = game.pile.remove(0);
+                            let action = ActionType::Draw { cards: vec![card.get_id()] };
+                            (card, action)
+                        }
+                        Some(_) => {
+                            // the card is not played, it is drawn like the other cards
+                            let cards = draw_cards(info, game, player_index, 1);
+                            let mut draw_action = PlayAction::new();
+                            draw_action.targets.push(ActionTarget { player_id, action: ActionType::Draw { cards }, effect: String::new() });
+                            info.actions.push(draw_action);
+                            return Ok(());
+                        }
+                        None => { return Ok(()); }
+                    }
+                }
+                NestedPlaySource::TargetDiscard => {
+                    let target = match target_indices.first() {
+                        Some(&i) => &mut game.players[i],
+                        None => { return Ok(()); }
+                    };
+                    let card = match target.discard_cards.pop() {
+                        Some(card) => card,
+                        None => { return Ok(()); }
+                    };
+                    let action = ActionType::DrawDiscard { from: target.id, cards: vec![card.get_id()] };
+                    (card, action)
+                }
+            };
+
+            let mut draw_action = PlayAction::new();
+            draw_action.targets.push(ActionTarget { player_id, action, effect: String::new() });
+            info.actions.push(draw_action);
+
+            if nested_play.filter.is_matching(card.as_ref()) {
+                let card_id = card.get_id();
+                if let Some(nested_info) = game.play_nested_card(player_index, card, target_indices, nested_play.attack_mul, buffs_used)? {
+                    let mut play_action = PlayAction::new();
+                    play_action.targets.push(ActionTarget { player_id, action: ActionType::Play { card: card_id }, effect: String::new() });
+                    info.actions.push(play_action);
+                    info.actions.extend(nested_info.actions);
+                }
+            } else {
+                game.players[player_index].hand_cards.push(card);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_game_effects(&self, _info: &mut PlayInfo, game: &mut Game, _player_index: usize, _target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        for effect in self.get_game_effects() {
+            effect.apply(game);
         }
 
         Ok(())
@@ -224,6 +693,40 @@ pub trait Card: Sync + Send + Debug + CardClone {
 
     /// Buffs are granted after the card is played
     fn get_buffs(&self) -> Vec<Box<dyn Buff>> { Vec::with_capacity(0) }
+    /// Common effects applied by the play() impl from Card trait (None if the card doesn't apply them)
+    fn get_effects(&self) -> Option<&CardEffects> { None }
+    /// Debuffs are given to the targets when the card is played
+    fn get_debuffs(&self) -> Vec<Box<dyn Buff>> { self.get_effects().map(|e| e.debuffs.clone()).unwrap_or_default() }
+    /// Effects applied to the game when the card is played
+    fn get_game_effects(&self) -> Vec<GameEffect> { self.get_effects().map(|e| e.game_effects.clone()).unwrap_or_default() }
+    /// Players drawing the cards of the card
+    fn get_draw_scope(&self) -> DrawScope { self.get_effects().map(|e| e.draw_scope).unwrap_or_default() }
+    /// Cards discarded from the hands of players when the card is played
+    fn get_discard(&self) -> Option<HandDiscard> { self.get_effects().and_then(|e| e.discard.clone()) }
+    /// Buffs removed from the targets when the card is played
+    fn get_dispel(&self) -> Option<Dispel> { self.get_effects().and_then(|e| e.dispel.clone()) }
+    /// If true, the player sees the cards in the hands of the targets after the card is played
+    fn get_reveal_hand(&self) -> bool { self.get_effects().is_some_and(|e| e.reveal_hand) }
+    /// Card drawn and played after the card is played
+    fn get_nested_play(&self) -> Option<NestedPlay> { self.get_effects().and_then(|e| e.nested_play.clone()) }
+    /// Cards moved between players when the card is played
+    fn get_card_moves(&self) -> Vec<CardMove> { self.get_effects().map(|e| e.card_moves.clone()).unwrap_or_default() }
+    /// Choice the player has to make before the card is played, with the choice used by default.
+    /// The choice is available in `game.choice` when the card is played.
+    /// By default, the player chooses the card to swap if the card swaps a card of the hand.
+    fn get_choice_request(&self, player_index: usize, card_index: usize, _target_indices: &Vec<usize>, game: &Game) -> Option<(ChoiceRequest, Choice)> {
+        if !self.get_card_moves().contains(&CardMove::SwapWithTargetDiscard) {
+            return None;
+        }
+
+        // the played card can't be chosen
+        let default_index = if card_index == 0 { 1 } else { 0 };
+        if default_index >= game.players[player_index].hand_cards.len() {
+            return None;
+        }
+
+        Some((ChoiceRequest::HandCards { min: 1, max: 1 }, Choice::HandCards { cards: vec![default_index] }))
+    }
 
     fn get_damage_effect(&self) -> EffectId {
         match self.get_element() {
@@ -237,14 +740,21 @@ pub trait Card: Sync + Send + Debug + CardClone {
     fn get_heal_effect(&self) -> EffectId { EffectId::from("heal_regular") }
 
     // basic validate_targets impl (only check if target count is equal to targets len)
+    /// Check if the player has to choose the targets of the card
+    fn needs_targets(&self) -> bool {
+        // no targets if only heal and/or draw for the player, or discard from the hand of the player
+        self.get_draw_scope() == DrawScope::Targets
+            || !((self.get_attack() == 0 && self.get_attack_modifier().is_none())
+            && ((self.get_heal() > 0 || self.get_heal_modifier().is_some())
+            || (self.get_draw() > 0 || self.get_draw_modifier().is_some())
+            || matches!(self.get_discard(), Some(HandDiscard { scope: DiscardScope::Caster, .. }))))
+    }
+
     fn validate_targets(&self, targets: &Vec<&Player>) -> Result<(), String> {
         println!("Validate targets: target type is {:?}", self.get_target_type());
 
         let expected =  {
-            // no targets if only heal and/or draw
-            if (self.get_attack() == 0 && self.get_attack_modifier().is_none())
-                && ((self.get_heal() > 0 || self.get_heal_modifier().is_some())
-                || (self.get_draw() > 0 || self.get_draw_modifier().is_some())) { 0 }
+            if !self.needs_targets() { 0 }
             else {
                 clamp(1, MAX_PLAYERS - 1,
                     match self.get_target_type() {
@@ -306,7 +816,8 @@ pub struct BasicCard {
     pub heal_modifier: Option<Box<dyn Modifier>>,
     pub draw: u32,
     pub draw_modifier: Option<Box<dyn Modifier>>,
-    pub buffs: Vec<Box<dyn Buff>>
+    pub buffs: Vec<Box<dyn Buff>>,
+    pub effects: CardEffects,
 }
 
 impl Card for BasicCard {
@@ -324,12 +835,20 @@ impl Card for BasicCard {
     fn get_stars(&self) -> Stars { self.stars }
     fn get_target_type(&self) -> TargetType { self.target_type }
     fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { Some(&self.effects) }
 }
 
 
-pub fn check_apply_attack_buffs(amount: u32, buffs: &Vec<Box<dyn Buff>>, card_element: Element, card_kind: Kind, card_stars: Stars, buffs_used: &mut HashSet<usize>) -> u32 {
+/// Apply the attack buffs of the player to the attack of a card.
+/// The UntilUsed buffs used by the previous plays of the context are not applied again,
+/// and the attack is multiplied by the multiplier of the context.
+pub fn check_apply_attack_buffs(amount: u32, buffs: &Vec<Box<dyn Buff>>, context: &PlayContext, card_element: Element, card_kind: Kind, card_stars: Stars, buffs_used: &mut HashSet<usize>) -> u32 {
     let mut amount = amount;
     for (idx, buff) in buffs.iter().enumerate() {
+        if buff.get_lifetime() == BuffLifeTime::UntilUsed && context.buffs_used.contains(&idx) {
+            continue;
+        }
+
         match buff.get_type() {
             BuffType::Attack { value: _, op: _, elements: _, kinds: _, stars: _ } => {
                 if buff.is_applicable(card_element, card_kind, card_stars) {
@@ -341,5 +860,5 @@ pub fn check_apply_attack_buffs(amount: u32, buffs: &Vec<Box<dyn Buff>>, card_el
         }
     }
 
-    amount
+    amount * context.attack_mul
 }
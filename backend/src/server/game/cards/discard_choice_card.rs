@@ -0,0 +1,109 @@
+use std::collections::HashSet;
+
+use super::card::{check_apply_attack_buffs, Card, CardEffects, CardId, Element, Kind, Stars, TargetType};
+use super::super::buffs::Buff;
+use super::super::choice::{Choice, ChoiceRequest};
+use super::super::game::Game;
+use super::super::play_info::{PlayAction, PlayInfo, ActionTarget, ActionType};
+
+
+/// Card variant that lets the player discard the cards of their choice.
+/// Each discarded card deals `attack_per_card` to the targets.
+#[derive(Debug, Clone)]
+pub struct DiscardChoiceCard {
+    pub id: CardId,
+    pub name: String,
+    pub element: Element,
+    pub stars: Stars,
+    pub kind: Kind,
+    pub desc: String,
+    pub target_type: TargetType,
+    pub attack_per_card: u32,
+    pub buffs: Vec<Box<dyn Buff>>,
+    pub effects: CardEffects,
+}
+
+impl DiscardChoiceCard {
+    /// Hand indices of the cards chosen by the player
+    fn get_chosen_cards(&self, game: &Game) -> Vec<usize> {
+        match &game.choice {
+            Some(Choice::HandCards { cards }) => cards.clone(),
+            _ => Vec::new()
+        }
+    }
+}
+
+impl Card for DiscardChoiceCard {
+    fn get_id(&self) -> CardId { self.id }
+    fn get_name(&self) -> String { String::from(&self.name) }
+    fn get_attack(&self) -> u32 { self.attack_per_card }
+    fn get_description(&self) -> String { String::from(&self.desc) }
+    fn get_kind(&self) -> Kind { self.kind }
+    fn get_element(&self) -> Element { self.element }
+    fn get_stars(&self) -> Stars { self.stars }
+    fn get_target_type(&self) -> TargetType { self.target_type }
+    fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { Some(&self.effects) }
+
+    fn get_choice_request(&self, player_index: usize, _card_index: usize, _target_indices: &Vec<usize>, game: &Game) -> Option<(ChoiceRequest, Choice)> {
+        // the played card can't be discarded
+        let max = game.players[player_index].hand_cards.len() as u32 - 1;
+        Some((ChoiceRequest::HandCards { min: 0, max }, Choice::HandCards { cards: Vec::new() }))
+    }
+
+    fn handle_discard(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, _target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        let mut indices = self.get_chosen_cards(game);
+        // remove from the end to keep indices valid
+        indices.sort();
+        indices.reverse();
+
+        let player = &mut game.players[player_index];
+        for &idx in indices.iter() {
+            let card = player.hand_cards.remove(idx);
+            player.discard_cards.push(card);
+        }
+
+        if !indices.is_empty() {
+            let mut discard_action = PlayAction::new();
+            discard_action.targets.push(ActionTarget {
+                player_id: player.id,
+                action: ActionType::Discard { cards: indices },
+                effect: String::new()
+            });
+            info.actions.push(discard_action);
+        }
+
+        Ok(())
+    }
+
+    fn handle_attack(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        let amount = self.attack_per_card * self.get_chosen_cards(game).len() as u32;
+
+        if amount > 0 {
+            for &target_index in target_indices {
+                let mut attack_action: PlayAction = PlayAction::new();
+                let affinity = game.get_affinity(self.get_element(), target_index);
+
+                // use split_at_mut() to prevent warnings about mutable borrows
+                let (player, target) = if player_index < target_index {
+                    let (left, right) = game.players.split_at_mut(target_index);
+                    (&mut left[player_index], &mut right[0])
+                } else if player_index > target_index {
+                    let (left, right) = game.players.split_at_mut(player_index);
+                    (&mut right[0], &mut left[target_index])
+                } else {
+                    return Err("Target is player !".to_string());
+                };
+
+                // apply attack buffs
+                let amount = check_apply_attack_buffs(amount, &player.buffs, &game.play_context, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
+
+                let action_target = target.damage(amount, affinity, self.get_damage_effect());
+                attack_action.targets.push(action_target);
+                info.actions.push(attack_action);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::card::{Card, CardEffects, CardFilter, CardId, Element, Kind, Stars, TargetType};
+use super::super::modifiers::Modifier;
+use super::super::buffs::Buff;
+use super::super::choice::{Choice, ChoiceRequest};
+use super::super::game::Game;
+use super::super::play_info::{PlayAction, PlayInfo, ActionTarget, ActionType};
+
+
+/// Which discard pile the cards are drawn from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub enum DiscardSource {
+    /// discard pile of the player
+    #[default]
+    Own,
+    /// discard pile of each target
+    Target,
+    /// discard pile chosen by the player (its own or an opponent's one)
+    Any,
+}
+
+
+/// Card variant that draws cards from a discard pile instead of the pile.
+/// Attack, heal and draw are handled like BasicCard.
+#[derive(Debug, Clone)]
+pub struct DiscardDrawCard {
+    pub id: CardId,
+    pub name: String,
+    pub element: Element,
+    pub stars: Stars,
+    pub kind: Kind,
+    pub desc: String,
+    pub target_type: TargetType,
+    pub attack: u32,
+    pub attack_modifier: Option<Box<dyn Modifier>>,
+    pub heal: u32,
+    pub heal_modifier: Option<Box<dyn Modifier>>,
+    pub draw: u32,
+    pub draw_modifier: Option<Box<dyn Modifier>>,
+    pub discard_source: DiscardSource,
+    /// amount of cards drawn from the discard pile, all matching cards are drawn if not set
+    pub discard_draw: Option<u32>,
+    pub discard_draw_modifier: Option<Box<dyn Modifier>>,
+    pub discard_filter: CardFilter,
+    pub buffs: Vec<Box<dyn Buff>>,
+    pub effects: CardEffects,
+}
+
+impl DiscardDrawCard {
+    /// Indices of the players whose discard piles are used
+    fn get_source_indices(&self, game: &Game, player_index: usize, target_indices: &[usize]) -> Vec<usize> {
+        match self.discard_source {
+            DiscardSource::Own => vec![player_index],
+            DiscardSource::Target => target_indices.to_vec(),
+            DiscardSource::Any => {
+                let chosen = match &game.choice {
+                    Some(Choice::DiscardPile { player_id }) => game.players.iter().position(|p| p.id == *player_id),
+                    _ => None
+                };
+                vec![chosen.unwrap_or_else(|| self.get_best_source_index(game, player_index))]
+            }
+        }
+    }
+
+    /// Index of the player with the most matching cards in their discard pile (the player is preferred if equal)
+    fn get_best_source_index(&self, game: &Game, player_index: usize) -> usize {
+        game.players.iter().enumerate()
+            .max_by_key(|(idx, p)| (
+                p.discard_cards.iter().filter(|c| self.discard_filter.is_matching(c.as_ref())).count(),
+                *idx == player_index
+            ))
+            .map(|(idx, _)| idx)
+            .unwrap_or(player_index)
+    }
+}
+
+impl Card for DiscardDrawCard {
+    fn get_id(&self) -> CardId { self.id }
+    fn get_name(&self) -> String { String::from(&self.name) }
+    fn get_attack(&self) -> u32 { self.attack }
+    fn get_attack_modifier(&self) -> Option<Box<dyn Modifier>> { self.attack_modifier.clone() }
+    fn get_heal(&self) -> u32 { self.heal }
+    fn get_heal_modifier(&self) -> Option<Box<dyn Modifier>> { self.heal_modifier.clone() }
+    fn get_draw(&self) -> u32 { self.draw }
+    fn get_draw_modifier(&self) -> Option<Box<dyn Modifier>> { self.draw_modifier.clone() }
+    fn get_description(&self) -> String { String::from(&self.desc) }
+    fn get_kind(&self) -> Kind { self.kind }
+    fn get_element(&self) -> Element { self.element }
+    fn get_stars(&self) -> Stars { self.stars }
+    fn get_target_type(&self) -> TargetType { self.target_type }
+    fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { Some(&self.effects) }
+
+    fn get_choice_request(&self, player_index: usize, _card_index: usize, _target_indices: &Vec<usize>, game: &Game) -> Option<(ChoiceRequest, Choice)> {
+        match self.discard_source {
+            DiscardSource::Any => {
+                let request = ChoiceRequest::DiscardPile { players: game.players.iter().map(|p| p.id).collect() };
+                let default = Choice::DiscardPile { player_id: game.players[self.get_best_source_index(game, player_index)].id };
+                Some((request, default))
+            }
+            _ => None
+        }
+    }
+
+    fn handle_discard_draw(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, dice_roll: u8, dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        for source_index in self.get_source_indices(game, player_index, target_indices) {
+            let (amount, dice_rolls, player_dice_id) = {
+                let player = &game.players[player_index];
+                if let Some(modifier) = &self.discard_draw_modifier {
+                    let (amount, dice_rolls, player_dice_id) = modifier.compute_rolls(self.discard_draw.unwrap_or(0), player, &game.players[source_index], Some(dice_roll), &mut game.rng);
+                    (Some(amount), dice_rolls, player_dice_id)
+                } else { (self.discard_draw, Vec::new(), -1) }
+            };
+
+            // take the cards on top of the discard pile first
+            let source = &mut game.players[source_index];
+            let mut indices: Vec<usize> = source.discard_cards.iter().enumerate()
+                .filter(|(_, card)| self.discard_filter.is_matching(card.as_ref()))
+                .map(|(idx, _)| idx)
+                .rev()
+                .collect();
+
+            if let Some(amount) = amount {
+                indices.truncate(amount as usize);
+            }
+
+            let source_id = source.id;
+            let cards: Vec<Box<dyn Card>> = indices.iter()
+                .map(|&idx| source.discard_cards.remove(idx))
+                .collect();
+
+            if !cards.is_empty() {
+                let mut draw_action = PlayAction::new();
+
+                // show dice anim on client only if this is the first time the dice is used
+                draw_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
+
+                let player = &mut game.players[player_index];
+                draw_action.targets.push(ActionTarget {
+                    player_id: player.id,
+                    action: ActionType::DrawDiscard { from: source_id, cards: cards.iter().map(|c| c.get_id()).collect() },
+                    effect: String::new()
+                });
+                player.hand_cards.extend(cards);
+                info.actions.push(draw_action);
+            }
+        }
+
+        Ok(())
+    }
+
+    // drawing from the discard pile of targets requires targets
+    fn needs_targets(&self) -> bool {
+        self.discard_source == DiscardSource::Target
+            || self.get_attack() > 0 || self.get_attack_modifier().is_some()
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::card::{check_apply_attack_buffs, draw_cards, refill_pile, roll_dice, Card, CardFilter, CardId, Element, Kind, PlayCondition, Stars, TargetType};
+use super::super::buffs::{Buff, BuffLifeTime, BuffType, BuffVariant};
+use super::super::game::Game;
+use super::super::modifiers::ModifierInfo;
+use super::super::play_info::{PlayAction, PlayInfo, ActionTarget, ActionType};
+
+
+/// Maximum amount of times the steps of a Repeat step are run
+pub const MAX_REPEATS: u32 = 10;
+
+
+/// Players affected by an effect step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub enum EffectScope {
+    Caster,
+    #[default]
+    Targets,
+    /// all players except the caster, in turn order
+    Opponents,
+    /// all players in turn order, starting with the caster
+    All,
+}
+
+fn default_caster_scope() -> EffectScope { EffectScope::Caster }
+
+
+/// Step of the effect of an EffectCard.
+/// The amount of a step is computed for each affected player with the modifier of the step (if set).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum EffectStep {
+    Damage {
+        #[serde(default)]
+        scope: EffectScope,
+        #[serde(default)]
+        amount: u32,
+        #[serde(default)]
+        modifier: Option<ModifierInfo>,
+    },
+    Heal {
+        #[serde(default = "default_caster_scope")]
+        scope: EffectScope,
+        #[serde(default)]
+        amount: u32,
+        #[serde(default)]
+        modifier: Option<ModifierInfo>,
+    },
+    /// draw the first cards matching the filter in the pile
+    Draw {
+        #[serde(default = "default_caster_scope")]
+        scope: EffectScope,
+        #[serde(default)]
+        amount: u32,
+        #[serde(default)]
+        modifier: Option<ModifierInfo>,
+        #[serde(default)]
+        filter: CardFilter,
+    },
+    /// discard random cards matching the filter (all matching cards if amount is not set)
+    Discard {
+        #[serde(default)]
+        scope: EffectScope,
+        #[serde(default)]
+        amount: Option<u32>,
+        #[serde(default)]
+        modifier: Option<ModifierInfo>,
+        #[serde(default)]
+        filter: CardFilter,
+        /// if true, the cards that don't match the filter are discarded instead
+        #[serde(default)]
+        exclude: bool,
+    },
+    /// steal random cards matching the filter from the affected players (all matching cards if amount is not set)
+    Steal {
+        #[serde(default)]
+        scope: EffectScope,
+        #[serde(default)]
+        amount: Option<u32>,
+        #[serde(default)]
+        modifier: Option<ModifierInfo>,
+        #[serde(default)]
+        filter: CardFilter,
+    },
+    /// give a buff to the affected players
+    ApplyBuff {
+        #[serde(default = "default_caster_scope")]
+        scope: EffectScope,
+        buff: BuffVariant,
+    },
+    /// each affected player rolls the dice, the modifiers of the next steps use the roll of the player they are computed for
+    /// (the roll of the caster if the player did not roll)
+    RollDice {
+        #[serde(default = "default_caster_scope")]
+        scope: EffectScope,
+    },
+    /// run `steps` if the condition holds on the actions of the previous step, else run `otherwise`
+    Conditional {
+        condition: PlayCondition,
+        #[serde(default)]
+        steps: Vec<EffectStep>,
+        #[serde(default)]
+        otherwise: Vec<EffectStep>,
+    },
+    Repeat {
+        times: u32,
+        steps: Vec<EffectStep>,
+    },
+}
+
+impl EffectStep {
+    fn get_scope(&self) -> Option<EffectScope> {
+        match self {
+            EffectStep::Damage { scope, .. }
+            | EffectStep::Heal { scope, .. }
+            | EffectStep::Draw { scope, .. }
+            | EffectStep::Discard { scope, .. }
+            | EffectStep::Steal { scope, .. }
+            | EffectStep::ApplyBuff { scope, .. }
+            | EffectStep::RollDice { scope } => Some(*scope),
+            EffectStep::Conditional { .. } | EffectStep::Repeat { .. } => None,
+        }
+    }
+
+    /// Check if the step or its sub steps affect the targets chosen by the player
+    fn uses_targets(&self) -> bool {
+        match self {
+            EffectStep::Conditional { steps, otherwise, .. } => {
+                steps.iter().chain(otherwise.iter()).any(|s| s.uses_targets())
+            }
+            EffectStep::Repeat { steps, .. } => steps.iter().any(|s| s.uses_targets()),
+            _ => self.get_scope() == Some(EffectScope::Targets),
+        }
+    }
+}
+
+
+/// State of an EffectCard while its steps are run
+struct EffectState {
+    info: PlayInfo,
+    buffs_used: HashSet<usize>,
+    /// dice roll of the players by index
+    dice_rolls: HashMap<usize, u8>,
+    dice_roll_used: bool,
+    /// index of the first action of the previous step, used by Conditional steps
+    step_first_action: usize,
+}
+
+/// Compute the amount of a step for the player at `index`
+fn compute_amount(state: &mut EffectState, action: &mut PlayAction, game: &mut Game, player_index: usize, index: usize, amount: u32, modifier: &Option<ModifierInfo>) -> u32 {
+    match modifier {
+        Some(modifier) => {
+            let dice_roll = state.dice_rolls.get(&index).or_else(|| state.dice_rolls.get(&player_index)).cloned();
+            let (amount, dice_rolls, player_dice_id) = modifier.clone().into_boxed()
+                .compute_rolls(amount, &game.players[player_index], &game.players[index], dice_roll, &mut game.rng);
+            action.show_dice_rolls(dice_rolls, player_dice_id, &mut state.dice_roll_used);
+            amount
+        }
+        None => amount
+    }
+}
+
+
+/// Card variant whose effect is an ordered list of steps defined in the card data
+#[derive(Debug, Clone)]
+pub struct EffectCard {
+    pub id: CardId,
+    pub name: String,
+    pub element: Element,
+    pub stars: Stars,
+    pub kind: Kind,
+    pub desc: String,
+    pub target_type: TargetType,
+    pub steps: Vec<EffectStep>,
+    pub buffs: Vec<Box<dyn Buff>>
+}
+
+impl EffectCard {
+    fn get_player_indices(&self, scope: EffectScope, game: &Game, player_index: usize, target_indices: &[usize]) -> Vec<usize> {
+        match scope {
+            EffectScope::Caster => vec![player_index],
+            EffectScope::Targets => target_indices.to_vec(),
+            // dead players are not affected
+            EffectScope::Opponents => game.players_in_turn_order(player_index).into_iter()
+                .skip(1)
+                .filter(|&i| game.players[i].health > 0)
+                .collect(),
+            EffectScope::All => game.players_in_turn_order(player_index).into_iter()
+                .filter(|&i| game.players[i].health > 0)
+                .collect(),
+        }
+    }
+
+    fn run_steps(&self, steps: &[EffectStep], state: &mut EffectState, game: &mut Game, player_index: usize, target_indices: &[usize]) -> Result<(), String> {
+        for step in steps {
+            // stop if the game is over
+            if game.players.iter().filter(|p| p.health > 0).count() <= 1 {
+                break;
+            }
+
+            match step {
+                EffectStep::Conditional { condition, steps, otherwise } => {
+                    let first_action = state.step_first_action;
+                    if condition.check(&mut state.info, first_action, &game.players[player_index], &mut game.rng) {
+                        self.run_steps(steps, state, game, player_index, target_indices)?;
+                    } else {
+                        self.run_steps(otherwise, state, game, player_index, target_indices)?;
+                    }
+                }
+                EffectStep::Repeat { times, steps } => {
+                    for _ in 0..(*times).min(MAX_REPEATS) {
+                        self.run_steps(steps, state, game, player_index, target_indices)?;
+                    }
+                }
+                _ => {
+                    state.step_first_action = state.info.actions.len();
+                    self.run_step(step, state, game, player_index, target_indices)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_step(&self, step: &EffectStep, state: &mut EffectState, game: &mut Game, player_index: usize, target_indices: &[usize]) -> Result<(), String> {
+        let player_id = game.players[player_index].id;
+        // unwrap: Conditional and Repeat steps are handled by run_steps()
+        let player_indices = self.get_player_indices(step.get_scope().unwrap(), game, player_index, target_indices);
+
+        match step {
+            EffectStep::Damage { amount, modifier, .. } => {
+                for i in player_indices {
+                    let mut attack_action = PlayAction::new();
+                    let amount = compute_amount(state, &mut attack_action, game, player_index, i, *amount, modifier);
+                    let amount = check_apply_attack_buffs(amount, &game.players[player_index].buffs, &game.play_context, self.get_element(), self.get_kind(), self.get_stars(), &mut state.buffs_used);
+
+                    let affinity = game.get_affinity(self.get_element(), i);
+                    let action_target = game.players[i].damage(amount, affinity, self.get_damage_effect());
+                    attack_action.targets.push(action_target);
+                    state.info.actions.push(attack_action);
+                }
+            }
+            EffectStep::Heal { amount, modifier, .. } => {
+                for i in player_indices {
+                    let mut heal_action = PlayAction::new();
+                    let amount = compute_amount(state, &mut heal_action, game, player_index, i, *amount, modifier);
+
+                    let action_target = game.players[i].heal(amount, self.get_heal_effect());
+                    heal_action.targets.push(action_target);
+                    state.info.actions.push(heal_action);
+                }
+            }
+            EffectStep::Draw { amount, modifier, filter, .. } => {
+                for i in player_indices {
+                    let mut draw_action = PlayAction::new();
+                    let amount = compute_amount(state, &mut draw_action, game, player_index, i, *amount, modifier);
+
+                    let drawn_cards = if *filter == CardFilter::default() {
+                        draw_cards(&mut state.info, game, i, amount)
+                    } else {
+                        // first matching cards in the pile
+                        refill_pile(&mut state.info, game, i, amount);
+                        let mut drawn_cards = Vec::new();
+                        while (drawn_cards.len() as u32) < amount {
+                            match game.pile.iter().position(|c| filter.is_matching(c.as_ref())) {
+                                Some(idx) => {
+                                    let card = game.pile.remove(idx);
+                                    drawn_cards.push(card.get_id());
+                                    game.players[i].hand_cards.push(card);
+                                }
+                                None => break
+                            }
+                        }
+                        drawn_cards
+                    };
+
+                    if !drawn_cards.is_empty() {
+                        draw_action.targets.push(ActionTarget {
+                            player_id: game.players[i].id,
+                            action: ActionType::Draw { cards: drawn_cards },
+                            effect: String::new()
+                        });
+                        state.info.actions.push(draw_action);
+                    }
+                }
+            }
+            EffectStep::Discard { amount, modifier, filter, exclude, .. } => {
+                let mut discard_action = PlayAction::new();
+                for i in player_indices {
+                    let amount = amount.map(|amount| compute_amount(state, &mut discard_action, game, player_index, i, amount, modifier));
+
+                    // the played card is still in hand, it must not be discarded
+                    let discarded = game.players[i].discard_hand_cards(filter, *exclude, amount, Some(self.get_id()), &mut game.rng);
+                    if !discarded.is_empty() {
+                        discard_action.targets.push(ActionTarget {
+                            player_id: game.players[i].id,
+                            action: ActionType::Discard { cards: discarded },
+                            effect: String::new()
+                        });
+                    }
+                }
+
+                if !discard_action.targets.is_empty() || !discard_action.dice_rolls.is_empty() {
+                    state.info.actions.push(discard_action);
+                }
+            }
+            EffectStep::Steal { amount, modifier, filter, .. } => {
+                for i in player_indices {
+                    if i == player_index {
+                        continue;
+                    }
+
+                    let mut steal_action = PlayAction::new();
+                    let amount = amount.map(|amount| compute_amount(state, &mut steal_action, game, player_index, i, amount, modifier));
+
+                    let cards = game.players[i].take_hand_cards(filter, amount, None, &mut game.rng);
+                    if !cards.is_empty() {
+                        steal_action.targets.push(ActionTarget {
+                            player_id: game.players[i].id,
+                            action: ActionType::Steal {
+                                to: player_id,
+                                cards: cards.iter().map(|c| c.get_id()).collect()
+                            },
+                            effect: String::new()
+                        });
+                        state.info.actions.push(steal_action);
+                        game.players[player_index].hand_cards.extend(cards);
+                    }
+                }
+            }
+            EffectStep::ApplyBuff { buff, .. } => {
+                let mut buff_action = PlayAction::new();
+                for i in player_indices {
+                    let mut buff = buff.clone().into_boxed();
+                    if i == player_index {
+                        // same as the buffs granted after the card is played, the buff is not used by this card
+                        if buff.get_lifetime() == BuffLifeTime::UntilNextTurnEnd {
+                            buff.set_lifetime(BuffLifeTime::Turns { delay: 1, turns: 1 });
+                        }
+                    }
+
+                    // the buffs given to the caster are not debuffs
+                    let action = if i == player_index {
+                        ActionType::Buff { buff: buff.as_variant() }
+                    } else {
+                        ActionType::Debuff { buff: buff.as_variant() }
+                    };
+                    buff_action.targets.push(ActionTarget {
+                        player_id: game.players[i].id,
+                        action,
+                        effect: String::new()
+                    });
+                    game.players[i].buffs.push(buff);
+                }
+
+                if !buff_action.targets.is_empty() {
+                    state.info.actions.push(buff_action);
+                }
+            }
+            EffectStep::RollDice { .. } => {
+                for i in player_indices {
+                    let dice_roll = roll_dice(&mut state.info, &mut game.rng, game.players[i].id);
+                    state.dice_rolls.insert(i, dice_roll);
+                }
+                // the rolls are already shown
+                state.dice_roll_used = true;
+            }
+            EffectStep::Conditional { .. } | EffectStep::Repeat { .. } => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl Card for EffectCard {
+    fn get_id(&self) -> CardId { self.id }
+    fn get_name(&self) -> String { String::from(&self.name) }
+    fn get_description(&self) -> String { String::from(&self.desc) }
+    fn get_kind(&self) -> Kind { self.kind }
+    fn get_element(&self) -> Element { self.element }
+    fn get_stars(&self) -> Stars { self.stars }
+    fn get_target_type(&self) -> TargetType { self.target_type }
+    fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+
+    // targets are only required if a step affects them
+    fn needs_targets(&self) -> bool {
+        self.steps.iter().any(|s| s.uses_targets())
+    }
+
+    fn play(&self, player_index: usize, target_indices: Vec<usize>, game: &mut Game) -> Result<(PlayInfo, HashSet<usize>), String> {
+        let targets = target_indices.iter().map(|i| &game.players[*i]).collect();
+        self.validate_targets(&targets)?;
+
+        let target_indices = {
+            if self.get_target_type() == TargetType::All || game.players[player_index].buffs.iter().any(|b| b.is_active() && b.get_type() == BuffType::TargetAll) {
+                game.players_in_turn_order(player_index).into_iter()
+                    .skip(1)
+                    .filter(|&i| game.players[i].health > 0)
+                    .collect()
+            } else { target_indices }
+        };
+
+        let mut state = EffectState {
+            info: PlayInfo::new(),
+            buffs_used: HashSet::new(),
+            dice_rolls: HashMap::new(),
+            dice_roll_used: false,
+            step_first_action: 0,
+        };
+        // dice roll value to give to modifiers
+        state.dice_rolls.insert(player_index, game.roll_card_dice());
+
+        self.run_steps(&self.steps, &mut state, game, player_index, &target_indices)?;
+
+        Ok((state.info, state.buffs_used))
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use super::card::{Card, CardId, Element, Kind, Stars, TargetType};
+use super::card::{draw_cards, Card, CardId, Element, Kind, Stars, TargetType};
 use super::super::modifiers::Modifier;
 use super::super::buffs::{Buff, BuffType};
 use super::super::game::{Game, MAX_PLAYERS};
@@ -72,6 +72,7 @@ impl MultiActionCard {
         if self.get_attack_for_action(action_idx) > 0 || self.get_attack_modifier_for_action(action_idx).is_some() {
             for &target_index in target_indices {
                 let mut attack_action: PlayAction = PlayAction::new();
+                let affinity = game.get_affinity(self.get_element(), target_index);
 
                 // use split_at_mut() to prevent warnings about mutable borrows
                 let (player, target) = if player_index < target_index {
@@ -84,22 +85,18 @@ impl MultiActionCard {
                     return Err("Target is player !".to_string());
                 };
                 
-                let (amount, dice_roll, player_dice_id) = {
+                let (amount, dice_rolls, player_dice_id) = {
                     if let Some(modifier) = self.get_attack_modifier_for_action(action_idx) {
-                        modifier.compute(self.get_attack_for_action(action_idx), player, target, Some(dice_roll))
-                    } else { (self.get_attack_for_action(action_idx), 0, -1) }
+                        modifier.compute_rolls(self.get_attack_for_action(action_idx), player, target, Some(dice_roll), &mut game.rng)
+                    } else { (self.get_attack_for_action(action_idx), Vec::new(), -1) }
                 };
 
                 // show dice anim on client only if this is the first time the dice is used
-                if !*dice_roll_used && player_dice_id != -1 {
-                    attack_action.dice_roll = dice_roll;
-                    attack_action.player_dice_id = player_dice_id;
-                    *dice_roll_used = true;
-                }
+                attack_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
-                let amount = check_apply_attack_buffs(amount, &player.buffs, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
+                let amount = check_apply_attack_buffs(amount, &player.buffs, &game.play_context, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
 
-                let action_target = target.damage(amount, self.get_damage_effect());
+                let action_target = target.damage(amount, affinity, self.get_damage_effect());
                 attack_action.targets.push(action_target);
                 info.actions.push(attack_action);
             }
@@ -114,18 +111,14 @@ impl MultiActionCard {
         if self.get_heal_for_action(action_idx) > 0 || self.get_heal_modifier_for_action(action_idx).is_some() {
             let mut heal_action: PlayAction = PlayAction::new();
 
-            let (amount, dice_roll, player_dice_id) = {
+            let (amount, dice_rolls, player_dice_id) = {
                 if let Some(modifier) = self.get_heal_modifier_for_action(action_idx) {
-                    modifier.compute(self.get_heal(), player, player, Some(dice_roll))
-                } else { (self.get_heal_for_action(action_idx), 0, -1) }
+                    modifier.compute_rolls(self.get_heal(), player, player, Some(dice_roll), &mut game.rng)
+                } else { (self.get_heal_for_action(action_idx), Vec::new(), -1) }
             };
 
             // show dice anim on client only if this is the first time the dice is used
-            if !*dice_roll_used && player_dice_id != -1 {
-                heal_action.dice_roll = dice_roll;
-                heal_action.player_dice_id = player_dice_id;
-                *dice_roll_used = true;
-            }
+            heal_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
             let action_target = player.heal(amount, self.get_heal_effect());
             heal_action.targets.push(action_target);
@@ -136,30 +129,25 @@ impl MultiActionCard {
     }
 
     fn handle_draw_for_action(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, _target_indices: &Vec<usize>, dice_roll: u8, dice_roll_used: &mut bool, action_idx: usize, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
-        let player = &mut game.players[player_index];
+        let player = &game.players[player_index];
 
         if self.get_draw_for_action(action_idx) > 0 || self.get_draw_modifier_for_action(action_idx).is_some() {
-            // FIXME handle discard cards collection later, for now we can't draw more cards than there is in pile
-
-            let (amount, dice_roll, player_dice_id) = {
+            let (amount, dice_rolls, player_dice_id) = {
                 if let Some(modifier) = self.get_draw_modifier_for_action(action_idx) {
-                    modifier.compute(self.get_draw_for_action(action_idx), player, player, Some(dice_roll))
-                } else { (self.get_draw_for_action(action_idx), 0, -1) }
+                    modifier.compute_rolls(self.get_draw_for_action(action_idx), player, player, Some(dice_roll), &mut game.rng)
+                } else { (self.get_draw_for_action(action_idx), Vec::new(), -1) }
             };
+            let player_id = player.id;
 
-            let drawn_cards = Game::give_from_pile(&mut game.pile, player, amount as usize);
+            let drawn_cards = draw_cards(info, game, player_index, amount);
             if drawn_cards.len() > 0 {
                 let mut draw_action = PlayAction::new();
                 
                 // show dice anim on client only if this is the first time the dice is used
-                if !*dice_roll_used && player_dice_id != -1 {
-                    draw_action.dice_roll = dice_roll;
-                    draw_action.player_dice_id = player_dice_id;
-                    *dice_roll_used = true;
-                }
+                draw_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
                 draw_action.targets.push(ActionTarget {
-                    player_id: player.id,
+                    player_id,
                     action: ActionType::Draw { cards: drawn_cards },    // FIXME set to -1 when sending to clients that aren't the current player
                     effect: String::new()
                 });
@@ -185,7 +173,7 @@ impl Card for MultiActionCard {
 
         for action_idx in 0..self.actions {
             let target_indices = {
-                if self.get_target_type_for_action(action_idx) == TargetType::All || game.players[player_index].buffs.iter().any(|b| b.get_type() == BuffType::TargetAll) {
+                if self.get_target_type_for_action(action_idx) == TargetType::All || game.players[player_index].buffs.iter().any(|b| b.is_active() && b.get_type() == BuffType::TargetAll) {
                     game.players.iter().enumerate().filter(|(i, _)| *i != player_index).map(|(i, _)| i).collect()
                 } else { target_indices.clone() }
             };
@@ -193,7 +181,7 @@ impl Card for MultiActionCard {
             let targets = target_indices.iter().map(|i| &game.players[*i]).collect();
             match self.validate_targets_for_action(action_idx, &targets) {
                 Ok(_) => {
-                    let dice_roll = rand::random_range(0..6) + 1;   // dice roll value to give to modifiers
+                    let dice_roll = game.roll_card_dice();   // dice roll value to give to modifiers
                     let mut dice_roll_used = false;
 
                     self.handle_attack_for_action(&mut info, game, player_index, &target_indices, dice_roll, &mut dice_roll_used, action_idx, &mut buffs_used)?;
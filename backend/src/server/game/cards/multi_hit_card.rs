@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 use crate::server::game::cards::card::check_apply_attack_buffs;
 
-use super::card::{Card, CardId, Element, Kind, Stars};
+use super::card::{Card, CardEffects, CardId, Element, Kind, Stars};
 use super::super::modifiers::Modifier;
 use super::super::buffs::Buff;
 use super::super::game::Game;
@@ -25,7 +25,8 @@ pub struct MultiHitCard {
     pub heal_modifier: Option<Box<dyn Modifier>>,
     pub draw: u32,
     pub draw_modifier: Option<Box<dyn Modifier>>,
-    pub buffs: Vec<Box<dyn Buff>>
+    pub buffs: Vec<Box<dyn Buff>>,
+    pub effects: CardEffects,
 }
 
 impl Card for MultiHitCard {
@@ -41,12 +42,14 @@ impl Card for MultiHitCard {
     fn get_stars(&self) -> Stars { self.stars }
 
     fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { Some(&self.effects) }
 
     // Attack targets multiple times
     fn handle_attack(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, buffs_used: &mut HashSet<usize>) -> Result<(), String> {
         if self.get_attack() > 0 || self.get_attack_modifier().is_some() {
             for &target_index in target_indices {
                 let mut attack_action: PlayAction = PlayAction::new();
+                let affinity = game.get_affinity(self.get_element(), target_index);
 
                 // use split_at_mut() to prevent warnings about mutable borrows
                 let (player, target) = if player_index < target_index {
@@ -60,8 +63,8 @@ impl Card for MultiHitCard {
                 };
 
                 for attack in self.attacks.iter() {
-                    let amount = check_apply_attack_buffs(*attack, &player.buffs, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
-                    let action_target = target.damage(amount, self.get_damage_effect());
+                    let amount = check_apply_attack_buffs(*attack, &player.buffs, &game.play_context, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
+                    let action_target = target.damage(amount, affinity, self.get_damage_effect());
                     attack_action.targets.push(action_target);
                 }
 
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::server::game::cards::card::check_apply_attack_buffs;
 
-use super::card::{Card, CardId, Element, Kind, Stars, TargetType};
+use super::card::{refill_pile, Card, CardId, Element, Kind, Stars, TargetType};
 use super::super::game::Game;
 use super::super::play_info::{PlayAction, PlayInfo, ActionTarget, ActionType};
 use super::super::buffs::Buff;
@@ -25,12 +25,13 @@ pub enum PlayersRollsDiceCardActionType { Attack, Heal, Draw, }
 
 impl PlayersRollsDiceCardActionType {
     fn process_action(&self, amount: u32, player_index: usize, card: &dyn Card, game: &mut Game, buffs_used: &mut HashSet<usize>) -> ActionTarget {
+        let affinity = game.get_affinity(card.get_element(), player_index);
         let player = &mut game.players[player_index];
 
         match self {
             PlayersRollsDiceCardActionType::Attack => {
-                let amount = check_apply_attack_buffs(amount, &player.buffs, card.get_element(), card.get_kind(), card.get_stars(), buffs_used);
-                player.damage(amount, card.get_damage_effect())
+                let amount = check_apply_attack_buffs(amount, &player.buffs, &game.play_context, card.get_element(), card.get_kind(), card.get_stars(), buffs_used);
+                player.damage(amount, affinity, card.get_damage_effect())
             }
             PlayersRollsDiceCardActionType::Heal => {
                 player.heal(amount, card.get_heal_effect())
@@ -72,6 +73,10 @@ impl PlayersRollsDiceCardAction {
     fn process_dice_action(&self, card: &dyn Card, action_type: PlayersRollsDiceCardActionType, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, dice_rolls: &mut Vec<u32>, buffs_used: &mut HashSet<usize>) -> Result<(), String> {
         match self {
             PlayersRollsDiceCardAction::AffectsAllPlayers => {
+                if let PlayersRollsDiceCardActionType::Draw = action_type {
+                    refill_pile(info, game, player_index, dice_rolls.iter().sum());
+                }
+
                 let mut action: PlayAction = PlayAction::new();
 
                 for idx in 0..dice_rolls.len() {
@@ -93,6 +98,10 @@ impl PlayersRollsDiceCardAction {
                     .map(|(idx, _)| idx)
                     .collect();
 
+                if let PlayersRollsDiceCardActionType::Draw = action_type {
+                    refill_pile(info, game, player_index, amount * min_dice_roll_indexes.len() as u32);
+                }
+
                 let mut action: PlayAction = PlayAction::new();
 
                 for idx in min_dice_roll_indexes {
@@ -137,7 +146,7 @@ impl Card for PlayersRollsDiceCard {
                 };
 
                 let mut dice_rolls: Vec<u32> = Vec::with_capacity(target_indices.len() + 1);
-                let dice_roll = rand::random_range(0..6) + 1;   // dice roll value to give to modifiers
+                let dice_roll = game.roll_card_dice();   // dice roll value to give to modifiers
                 dice_rolls.push(dice_roll as u32);
 
                 // push action
@@ -151,7 +160,7 @@ impl Card for PlayersRollsDiceCard {
                 for &target_index in target_indices.iter() {
                     let mut dice_roll_action: PlayAction = PlayAction::new();
                     
-                    let dice_roll = rand::random_range(0..6) + 1;
+                    let dice_roll = game.roll_dice();
                     dice_rolls.push(dice_roll as u32);
 
                     dice_roll_action.dice_roll = dice_roll;
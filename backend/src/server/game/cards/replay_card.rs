@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+
+use super::card::{roll_dice, Card, CardEffects, CardId, Element, Kind, PlayCondition, Stars, TargetType};
+use super::super::buffs::Buff;
+use super::super::choice::{Choice, ChoiceRequest};
+use super::super::game::{Game, PlayContext};
+use super::super::modifiers::Modifier;
+use super::super::player::Player;
+use super::super::play_info::PlayInfo;
+
+
+/// Maximum amount of times a card can be played in a row
+pub const MAX_REPLAYS: u32 = 10;
+
+
+/// Card variant that plays its card again while a condition holds.
+/// Each play is added to the same PlayInfo, so the client shows every iteration.
+#[derive(Debug, Clone)]
+pub struct ReplayCard {
+    /// card played at each iteration (shares the id of the ReplayCard)
+    pub card: Box<dyn Card>,
+    pub condition: PlayCondition,
+    /// if true, the dice is rolled and checked before playing the card, else the condition is checked after
+    pub check_first: bool,
+    pub max_replays: u32,
+    pub buffs: Vec<Box<dyn Buff>>
+}
+
+impl Card for ReplayCard {
+    fn get_id(&self) -> CardId { self.card.get_id() }
+    fn get_name(&self) -> String { self.card.get_name() }
+    fn get_attack(&self) -> u32 { self.card.get_attack() }
+    fn get_attack_modifier(&self) -> Option<Box<dyn Modifier>> { self.card.get_attack_modifier() }
+    fn get_heal(&self) -> u32 { self.card.get_heal() }
+    fn get_heal_modifier(&self) -> Option<Box<dyn Modifier>> { self.card.get_heal_modifier() }
+    fn get_draw(&self) -> u32 { self.card.get_draw() }
+    fn get_draw_modifier(&self) -> Option<Box<dyn Modifier>> { self.card.get_draw_modifier() }
+    fn get_description(&self) -> String { self.card.get_description() }
+    fn get_kind(&self) -> Kind { self.card.get_kind() }
+    fn get_element(&self) -> Element { self.card.get_element() }
+    fn get_stars(&self) -> Stars { self.card.get_stars() }
+    fn get_target_type(&self) -> TargetType { self.card.get_target_type() }
+    fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { self.card.get_effects() }
+
+    fn validate_targets(&self, targets: &Vec<&Player>) -> Result<(), String> {
+        self.card.validate_targets(targets)
+    }
+
+    fn get_choice_request(&self, player_index: usize, card_index: usize, target_indices: &Vec<usize>, game: &Game) -> Option<(ChoiceRequest, Choice)> {
+        self.card.get_choice_request(player_index, card_index, target_indices, game)
+    }
+
+    fn play(&self, player_index: usize, target_indices: Vec<usize>, game: &mut Game) -> Result<(PlayInfo, HashSet<usize>), String> {
+        let targets = target_indices.iter().map(|i| &game.players[*i]).collect();
+        self.validate_targets(&targets)?;
+
+        let mut info: PlayInfo = PlayInfo::new();
+        let mut buffs_used: HashSet<usize> = HashSet::new();
+
+        // context of the card playing this card (nested plays)
+        let first_context = std::mem::take(&mut game.play_context);
+        let result = self.play_iterations(player_index, &target_indices, game, &first_context, &mut info, &mut buffs_used);
+        game.play_context = first_context;
+        result?;
+
+        Ok((info, buffs_used))
+    }
+}
+
+impl ReplayCard {
+    fn play_iterations(&self, player_index: usize, target_indices: &[usize], game: &mut Game, first_context: &PlayContext, info: &mut PlayInfo, buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        let player_id = game.players[player_index].id;
+
+        for _ in 0..self.max_replays {
+            // the card uses the dice roll checked by the condition
+            let dice_roll = if self.check_first {
+                let dice_roll = roll_dice(info, &mut game.rng, player_id);
+                if !self.condition.is_dice_roll_valid(dice_roll) {
+                    break;
+                }
+                Some(dice_roll)
+            } else { None };
+
+            // the UntilUsed buffs only apply to the first iteration
+            game.play_context = PlayContext {
+                buffs_used: first_context.buffs_used.union(buffs_used).cloned().collect(),
+                dice_roll,
+                attack_mul: first_context.attack_mul,
+            };
+
+            let first_action = info.actions.len();
+            let (iteration_info, iteration_buffs_used) = self.card.play(player_index, target_indices.to_vec(), game)?;
+            buffs_used.extend(iteration_buffs_used);
+            info.actions.extend(iteration_info.actions);
+
+            let replay = self.check_first || self.condition.check(info, first_action, &game.players[player_index], &mut game.rng);
+
+            // stop if the game is over
+            let remaining_players = game.players.iter().filter(|p| p.health > 0).count();
+            if !replay || remaining_players <= 1 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::game::buffs::{AttackBuff, BuffLifeTime};
+    use crate::server::game::card_info::CardInfo;
+    use crate::server::game::eval::EvalOp;
+    use crate::server::game::game::test_utils::make_game;
+
+    /// ReplayCard dealing 1 damage, replayed while the dice roll is valid (always valid)
+    fn make_card(max_replays: Option<u32>) -> Box<dyn Card> {
+        let max_replays = max_replays.map(|max| format!(r#","max_replays":{}"#, max)).unwrap_or_default();
+        let json = format!(r#"{{"name":"Test","element":"Fire","stars":"One","kind":"Spell","type":"ReplayCard",
+            "card":{{"type":"BasicCard","attack":1}},"condition":{{"type":"DiceRoll","values":[1,2,3,4,5,6]}},"check_first":true{}}}"#, max_replays);
+        serde_json::from_str::<CardInfo>(&json).unwrap().make_card()
+    }
+
+    #[test]
+    fn replays_are_capped() {
+        let card = make_card(None);
+        let mut game = make_game(2);
+        let health = game.players[1].health;
+
+        let (info, _) = card.play(0, vec![1], &mut game).unwrap();
+
+        assert_eq!(game.players[1].health, health - MAX_REPLAYS as i32);
+        assert_eq!(info.actions.iter().filter(|a| a.dice_roll > 0).count(), MAX_REPLAYS as usize);
+    }
+
+    #[test]
+    fn until_used_buffs_apply_once() {
+        let card = make_card(Some(3));
+        let mut game = make_game(2);
+        let health = game.players[1].health;
+        game.players[0].buffs.push(Box::new(AttackBuff {
+            value: 2,
+            op: EvalOp::Add,
+            elements: Vec::new(),
+            kinds: Vec::new(),
+            stars: Vec::new(),
+            lifetime: BuffLifeTime::UntilUsed,
+        }));
+
+        let (_, buffs_used) = card.play(0, vec![1], &mut game).unwrap();
+
+        // 1 + 2 for the first play, 1 for the next ones
+        assert_eq!(game.players[1].health, health - 5);
+        assert!(buffs_used.contains(&0));
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+
+use super::card::{Card, CardEffects, CardId, Element, Kind, PlayCondition, Stars, TargetType};
+use super::super::buffs::Buff;
+use super::super::choice::{Choice, ChoiceRequest};
+use super::super::game::Game;
+use super::super::player::Player;
+use super::super::play_info::PlayInfo;
+
+
+/// Card variant that goes back to the player's hand after being played, instead of the discard pile.
+/// If a condition is set, the card only goes back to the hand if the condition is met.
+#[derive(Debug, Clone)]
+pub struct ReturnCard {
+    /// card played (shares the id of the ReturnCard)
+    pub card: Box<dyn Card>,
+    pub condition: Option<PlayCondition>,
+    pub buffs: Vec<Box<dyn Buff>>
+}
+
+impl Card for ReturnCard {
+    fn get_id(&self) -> CardId { self.card.get_id() }
+    fn get_name(&self) -> String { self.card.get_name() }
+    fn get_description(&self) -> String { self.card.get_description() }
+    fn get_kind(&self) -> Kind { self.card.get_kind() }
+    fn get_element(&self) -> Element { self.card.get_element() }
+    fn get_stars(&self) -> Stars { self.card.get_stars() }
+    fn get_target_type(&self) -> TargetType { self.card.get_target_type() }
+    fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { self.card.get_effects() }
+
+    fn validate_targets(&self, targets: &Vec<&Player>) -> Result<(), String> {
+        self.card.validate_targets(targets)
+    }
+
+    fn get_choice_request(&self, player_index: usize, card_index: usize, target_indices: &Vec<usize>, game: &Game) -> Option<(ChoiceRequest, Choice)> {
+        self.card.get_choice_request(player_index, card_index, target_indices, game)
+    }
+
+    fn play(&self, player_index: usize, target_indices: Vec<usize>, game: &mut Game) -> Result<(PlayInfo, HashSet<usize>), String> {
+        let (mut info, buffs_used) = self.card.play(player_index, target_indices, game)?;
+
+        info.return_to_hand = match &self.condition {
+            Some(condition) => condition.check(&mut info, 0, &game.players[player_index], &mut game.rng),
+            None => true
+        };
+
+        Ok((info, buffs_used))
+    }
+}
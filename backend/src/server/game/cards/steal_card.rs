@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::card::{Card, CardEffects, CardFilter, CardId, Element, Kind, Stars, TargetType};
+use super::super::modifiers::Modifier;
+use super::super::buffs::Buff;
+use super::super::game::Game;
+use super::super::play_info::{PlayAction, PlayInfo, ActionTarget, ActionType};
+
+
+/// Describes which cards are moved from a hand to another
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CardTransfer {
+    /// amount of random cards to move, all matching cards are moved if not set
+    #[serde(default)]
+    pub amount: Option<u32>,
+    #[serde(default)]
+    pub filter: CardFilter,
+    /// the player recovers this amount of health for each card moved
+    #[serde(default)]
+    pub heal_per_card: u32,
+}
+
+
+/// Card variant that can steal cards from its targets and/or give cards to them.
+/// Attack, heal and draw are handled like BasicCard.
+#[derive(Debug, Clone)]
+pub struct StealCard {
+    pub id: CardId,
+    pub name: String,
+    pub element: Element,
+    pub stars: Stars,
+    pub kind: Kind,
+    pub desc: String,
+    pub target_type: TargetType,
+    pub attack: u32,
+    pub attack_modifier: Option<Box<dyn Modifier>>,
+    pub heal: u32,
+    pub heal_modifier: Option<Box<dyn Modifier>>,
+    pub draw: u32,
+    pub draw_modifier: Option<Box<dyn Modifier>>,
+    pub steal: Option<CardTransfer>,
+    pub give: Option<CardTransfer>,
+    pub buffs: Vec<Box<dyn Buff>>,
+    pub effects: CardEffects,
+}
+
+impl StealCard {
+    fn push_heal_per_card(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, heal_per_card: u32, card_count: usize) {
+        if heal_per_card > 0 && card_count > 0 {
+            let mut heal_action: PlayAction = PlayAction::new();
+            let action_target = game.players[player_index].heal(heal_per_card * card_count as u32, self.get_heal_effect());
+            heal_action.targets.push(action_target);
+            info.actions.push(heal_action);
+        }
+    }
+}
+
+impl Card for StealCard {
+    fn get_id(&self) -> CardId { self.id }
+    fn get_name(&self) -> String { String::from(&self.name) }
+    fn get_attack(&self) -> u32 { self.attack }
+    fn get_attack_modifier(&self) -> Option<Box<dyn Modifier>> { self.attack_modifier.clone() }
+    fn get_heal(&self) -> u32 { self.heal }
+    fn get_heal_modifier(&self) -> Option<Box<dyn Modifier>> { self.heal_modifier.clone() }
+    fn get_draw(&self) -> u32 { self.draw }
+    fn get_draw_modifier(&self) -> Option<Box<dyn Modifier>> { self.draw_modifier.clone() }
+    fn get_description(&self) -> String { String::from(&self.desc) }
+    fn get_kind(&self) -> Kind { self.kind }
+    fn get_element(&self) -> Element { self.element }
+    fn get_stars(&self) -> Stars { self.stars }
+    fn get_target_type(&self) -> TargetType { self.target_type }
+    fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { Some(&self.effects) }
+
+    // steal cards from each target
+    fn handle_steal(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        if let Some(steal) = &self.steal {
+            let mut stolen_count: usize = 0;
+
+            for &target_index in target_indices {
+                if target_index == player_index {
+                    return Err("Target is player !".to_string());
+                }
+
+                let cards = game.players[target_index].take_hand_cards(&steal.filter, steal.amount, None, &mut game.rng);
+                if !cards.is_empty() {
+                    let mut steal_action: PlayAction = PlayAction::new();
+                    steal_action.targets.push(ActionTarget {
+                        player_id: game.players[target_index].id,
+                        action: ActionType::Steal {
+                            to: game.players[player_index].id,
+                            cards: cards.iter().map(|c| c.get_id()).collect()
+                        },
+                        effect: String::new()
+                    });
+                    info.actions.push(steal_action);
+
+                    stolen_count += cards.len();
+                    game.players[player_index].hand_cards.extend(cards);
+                }
+            }
+
+            self.push_heal_per_card(info, game, player_index, steal.heal_per_card, stolen_count);
+        }
+
+        Ok(())
+    }
+
+    // give cards to each target
+    fn handle_give(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, _dice_roll: u8, _dice_roll_used: &mut bool, _buffs_used: &mut HashSet<usize>) -> Result<(), String> {
+        if let Some(give) = &self.give {
+            let mut given_count: usize = 0;
+
+            for &target_index in target_indices {
+                if target_index == player_index {
+                    return Err("Target is player !".to_string());
+                }
+
+                // the played card can't be given
+                let cards = game.players[player_index].take_hand_cards(&give.filter, give.amount, Some(self.get_id()), &mut game.rng);
+                if !cards.is_empty() {
+                    let mut give_action: PlayAction = PlayAction::new();
+                    give_action.targets.push(ActionTarget {
+                        player_id: game.players[target_index].id,
+                        action: ActionType::Give {
+                            from: game.players[player_index].id,
+                            cards: cards.iter().map(|c| c.get_id()).collect()
+                        },
+                        effect: String::new()
+                    });
+                    info.actions.push(give_action);
+
+                    given_count += cards.len();
+                    game.players[target_index].hand_cards.extend(cards);
+                }
+            }
+
+            self.push_heal_per_card(info, game, player_index, give.heal_per_card, given_count);
+        }
+
+        Ok(())
+    }
+
+    // stealing or giving always requires targets
+    fn needs_targets(&self) -> bool {
+        self.steal.is_some() || self.give.is_some()
+            || self.get_attack() > 0 || self.get_attack_modifier().is_some()
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::HashSet;
 
 use crate::server::game::cards::card::check_apply_attack_buffs;
 
-use super::card::{Card, CardId, Element, Kind, Stars, TargetType};
+use super::card::{draw_cards, Card, CardEffects, CardId, Element, Kind, Stars, TargetType};
 use super::super::modifiers::Modifier;
 use super::super::buffs::Buff;
 use super::super::game::Game;
@@ -25,7 +25,8 @@ pub struct TargetBothCard {
     pub heal_modifier: Option<Box<dyn Modifier>>,
     pub draw: u32,
     pub draw_modifier: Option<Box<dyn Modifier>>,
-    pub buffs: Vec<Box<dyn Buff>>
+    pub buffs: Vec<Box<dyn Buff>>,
+    pub effects: CardEffects,
 }
 
 impl Card for TargetBothCard {
@@ -44,31 +45,29 @@ impl Card for TargetBothCard {
     fn get_target_type(&self) -> TargetType { self.target_type }
 
     fn get_buffs(&self) -> Vec<Box<dyn Buff>> { self.buffs.clone() }
+    fn get_effects(&self) -> Option<&CardEffects> { Some(&self.effects) }
 
     fn handle_attack(&self, info: &mut PlayInfo, game: &mut Game, player_index: usize, target_indices: &Vec<usize>, dice_roll: u8, dice_roll_used: &mut bool, buffs_used: &mut HashSet<usize>) -> Result<(), String> {
         if self.get_attack() > 0 || self.get_attack_modifier().is_some() {
             let mut attack_self_action: PlayAction = PlayAction::new();
 
             // attack self
+            let affinity = game.get_affinity(self.get_element(), player_index);
             let player = &mut game.players[player_index];
 
-            let (amount, dice_roll, player_dice_id) = {
+            let (amount, dice_rolls, player_dice_id) = {
                 if let Some(modifier) = self.get_attack_modifier() {
-                    modifier.compute(self.get_attack(), player, player, Some(dice_roll))
-                } else { (self.get_attack(), 0, -1) }
+                    modifier.compute_rolls(self.get_attack(), player, player, Some(dice_roll), &mut game.rng)
+                } else { (self.get_attack(), Vec::new(), -1) }
             };
 
             // show dice anim on client only if this is the first time the dice is used
-            if !*dice_roll_used && player_dice_id != -1 {
-                attack_self_action.dice_roll = dice_roll;
-                attack_self_action.player_dice_id = player_dice_id;
-                *dice_roll_used = true;
-            }
+            attack_self_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
             // apply attack buffs
-            let amount = check_apply_attack_buffs(amount, &player.buffs, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
+            let amount = check_apply_attack_buffs(amount, &player.buffs, &game.play_context, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
 
-            let action_target = player.damage(amount, self.get_damage_effect());
+            let action_target = player.damage(amount, affinity, self.get_damage_effect());
             attack_self_action.targets.push(action_target);
             info.actions.push(attack_self_action);
 
@@ -76,6 +75,7 @@ impl Card for TargetBothCard {
             // attack targets
             for &target_index in target_indices {
                 let mut attack_action: PlayAction = PlayAction::new();
+                let affinity = game.get_affinity(self.get_element(), target_index);
 
                 // use split_at_mut() to prevent warnings about mutable borrows
                 let (player, target) = if player_index < target_index {
@@ -88,23 +88,19 @@ impl Card for TargetBothCard {
                     return Err("Target is player !".to_string());
                 };
                 
-                let (amount, dice_roll, player_dice_id) = {
+                let (amount, dice_rolls, player_dice_id) = {
                     if let Some(modifier) = self.get_attack_modifier() {
-                        modifier.compute(self.get_attack(), player, target, Some(dice_roll))
-                    } else { (self.get_attack(), 0, -1) }
+                        modifier.compute_rolls(self.get_attack(), player, target, Some(dice_roll), &mut game.rng)
+                    } else { (self.get_attack(), Vec::new(), -1) }
                 };
 
                 // show dice anim on client only if this is the first time the dice is used
-                if !*dice_roll_used && player_dice_id != -1 {
-                    attack_action.dice_roll = dice_roll;
-                    attack_action.player_dice_id = player_dice_id;
-                    *dice_roll_used = true;
-                }
+                attack_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
                 // apply attack buffs
-                let amount = check_apply_attack_buffs(amount, &player.buffs, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
+                let amount = check_apply_attack_buffs(amount, &player.buffs, &game.play_context, self.get_element(), self.get_kind(), self.get_stars(), buffs_used);
 
-                let action_target = target.damage(amount, self.get_damage_effect());
+                let action_target = target.damage(amount, affinity, self.get_damage_effect());
                 attack_action.targets.push(action_target);
                 info.actions.push(attack_action);
             }
@@ -129,18 +125,14 @@ impl Card for TargetBothCard {
                     return Err("Target is player !".to_string());
                 };
                 
-                let (amount, dice_roll, player_dice_id) = {
+                let (amount, dice_rolls, player_dice_id) = {
                     if let Some(modifier) = self.get_heal_modifier() {
-                        modifier.compute(self.get_heal(), target, player, Some(dice_roll))
-                    } else { (self.get_heal(), 0, -1) }
+                        modifier.compute_rolls(self.get_heal(), target, player, Some(dice_roll), &mut game.rng)
+                    } else { (self.get_heal(), Vec::new(), -1) }
                 };
 
                 // show dice anim on client only if this is the first time the dice is used
-                if !*dice_roll_used && player_dice_id != -1 {
-                    heal_target_action.dice_roll = dice_roll;
-                    heal_target_action.player_dice_id = player_dice_id;
-                    *dice_roll_used = true;
-                }
+                heal_target_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
                 let action_target = target.heal(amount, self.get_heal_effect());
                 heal_target_action.targets.push(action_target);
@@ -152,18 +144,14 @@ impl Card for TargetBothCard {
 
             let mut heal_action: PlayAction = PlayAction::new();
 
-            let (amount, dice_roll, player_dice_id) = {
+            let (amount, dice_rolls, player_dice_id) = {
                 if let Some(modifier) = self.get_heal_modifier() {
-                    modifier.compute(self.get_heal(), player, player, Some(dice_roll))
-                } else { (self.get_heal(), 0, -1) }
+                    modifier.compute_rolls(self.get_heal(), player, player, Some(dice_roll), &mut game.rng)
+                } else { (self.get_heal(), Vec::new(), -1) }
             };
 
             // show dice anim on client only if this is the first time the dice is used
-            if !*dice_roll_used && player_dice_id != -1 {
-                heal_action.dice_roll = dice_roll;
-                heal_action.player_dice_id = player_dice_id;
-                *dice_roll_used = true;
-            }
+            heal_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
             let action_target = player.heal(amount, self.get_heal_effect());
             heal_action.targets.push(action_target);
@@ -187,25 +175,22 @@ impl Card for TargetBothCard {
                     return Err("Target is player !".to_string());
                 };
                 
-                let (amount, dice_roll, player_dice_id) = {
+                let (amount, dice_rolls, player_dice_id) = {
                     if let Some(modifier) = self.get_draw_modifier() {
-                        modifier.compute(self.get_draw(), target, target, Some(dice_roll))
-                    } else { (self.get_draw(), 0, -1) }
+                        modifier.compute_rolls(self.get_draw(), target, target, Some(dice_roll), &mut game.rng)
+                    } else { (self.get_draw(), Vec::new(), -1) }
                 };
+                let target_id = target.id;
 
-                let drawn_cards = Game::give_from_pile(&mut game.pile, target, amount as usize);
+                let drawn_cards = draw_cards(info, game, target_index, amount);
                 if drawn_cards.len() > 0 {
                     let mut draw_target_action = PlayAction::new();
                     
                     // show dice anim on client only if this is the first time the dice is used
-                    if !*dice_roll_used && player_dice_id != -1 {
-                        draw_target_action.dice_roll = dice_roll;
-                        draw_target_action.player_dice_id = player_dice_id;
-                        *dice_roll_used = true;
-                    }
+                    draw_target_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
                     draw_target_action.targets.push(ActionTarget {
-                        player_id: target.id,
+                        player_id: target_id,
                         action: ActionType::Draw { cards: drawn_cards },    // FIXME set to -1 when sending to clients that aren't the current player
                         effect: String::new()
                     });
@@ -214,29 +199,24 @@ impl Card for TargetBothCard {
             }
 
             // do normal draw for current player
-            let player = &mut game.players[player_index];
-            
-            // FIXME handle discard cards collection later, for now we can't draw more cards than there is in pile
+            let player = &game.players[player_index];
 
-            let (amount, dice_roll, player_dice_id) = {
+            let (amount, dice_rolls, player_dice_id) = {
                 if let Some(modifier) = self.get_draw_modifier() {
-                    modifier.compute(self.get_draw(), player, player, Some(dice_roll))
-                } else { (self.get_draw(), 0, -1) }
+                    modifier.compute_rolls(self.get_draw(), player, player, Some(dice_roll), &mut game.rng)
+                } else { (self.get_draw(), Vec::new(), -1) }
             };
+            let player_id = player.id;
 
-            let drawn_cards = Game::give_from_pile(&mut game.pile, player, amount as usize);
+            let drawn_cards = draw_cards(info, game, player_index, amount);
             if drawn_cards.len() > 0 {
                 let mut draw_action = PlayAction::new();
                 
                 // show dice anim on client only if this is the first time the dice is used
-                if !*dice_roll_used && player_dice_id != -1 {
-                    draw_action.dice_roll = dice_roll;
-                    draw_action.player_dice_id = player_dice_id;
-                    *dice_roll_used = true;
-                }
+                draw_action.show_dice_rolls(dice_rolls, player_dice_id, dice_roll_used);
 
                 draw_action.targets.push(ActionTarget {
-                    player_id: player.id,
+                    player_id,
                     action: ActionType::Draw { cards: drawn_cards },    // FIXME set to -1 when sending to clients that aren't the current player
                     effect: String::new()
                 });
@@ -247,3 +227,25 @@ impl Card for TargetBothCard {
         Ok(())
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::server::game::card_info::CardInfo;
+    use crate::server::game::game::test_utils::make_game;
+
+    #[test]
+    fn every_dice_roll_is_reported() {
+        let json = r#"{"name":"Test","element":"Fire","stars":"One","kind":"Spell","type":"TargetBothCard","attack":0,
+            "attack_modifier":{"type":"DiceExprModifier","expr":{"type":"Sum","count":3}}}"#;
+        let mut game = make_game(2);
+        game.players[0].hand_cards.push(serde_json::from_str::<CardInfo>(json).unwrap().make_card());
+
+        let info = game.play_card(1, 0, vec![2]).unwrap();
+
+        assert_eq!(info.actions.len(), 2);
+        for action in info.actions.iter() {
+            assert_eq!(action.dice_rolls.len(), 3);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::player::{Player, PlayerId};
+
+
+/// Choice the player has to make to play a card
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum ChoiceRequest {
+    /// choose between `min` and `max` cards in hand (by hand index), the played card can't be chosen
+    HandCards { min: u32, max: u32 },
+    /// choose the discard pile of one of the players
+    DiscardPile { players: Vec<PlayerId> },
+    /// choose to attack the targets or to heal the player
+    AttackOrHeal,
+}
+
+/// Answer of the player to a ChoiceRequest
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum Choice {
+    HandCards { cards: Vec<usize> },
+    DiscardPile { player_id: PlayerId },
+    AttackOrHeal { heal: bool },
+}
+
+impl ChoiceRequest {
+    /// Check if the choice answers the request.
+    /// `card_index` is the index of the played card in the hand of the player.
+    pub fn validate(&self, choice: &Choice, player: &Player, card_index: usize) -> Result<(), String> {
+        match (self, choice) {
+            (ChoiceRequest::HandCards { min, max }, Choice::HandCards { cards }) => {
+                let unique: HashSet<&usize> = cards.iter().collect();
+                if unique.len() != cards.len() || cards.iter().any(|&i| i >= player.hand_cards.len() || i == card_index) {
+                    Err("Invalid hand cards".to_string())
+                } else if (cards.len() as u32) < *min || (cards.len() as u32) > *max {
+                    Err("Invalid hand card count".to_string())
+                } else { Ok(()) }
+            }
+            (ChoiceRequest::DiscardPile { players }, Choice::DiscardPile { player_id }) => {
+                if players.contains(player_id) { Ok(()) } else { Err("Invalid discard pile".to_string()) }
+            }
+            (ChoiceRequest::AttackOrHeal, Choice::AttackOrHeal { .. }) => Ok(()),
+            _ => Err("Choice does not match the request".to_string())
+        }
+    }
+}
+
+
+/// Card played by the current player, waiting for the player's choice to be resolved
+#[derive(Debug, Clone)]
+pub struct PendingChoice {
+    pub player_id: PlayerId,
+    pub card_index: usize,
+    pub targets: Vec<PlayerId>,
+    pub request: ChoiceRequest,
+    /// choice used if the player does not answer before the end of the turn
+    pub default: Choice,
+}
@@ -2,15 +2,19 @@ use std::collections::HashSet;
 use std::time::Duration;
 
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::rng;
 
-use super::cards::card::{Card, CardId};
+use super::affinity::{AffinitySource, AffinityTable, NEUTRAL_AFFINITY};
+use super::cards::card::{Card, CardId, Element};
 use super::player::{Player, PlayerId};
-use super::play_info::PlayInfo;
-use super::buffs::BuffLifeTime;
+use super::play_info::{ActionType, PlayAction, PlayInfo};
+use super::choice::{Choice, PendingChoice};
+use super::buffs::{BuffLifeTime, BuffType, BuffVariant, TickTime};
 
-use crate::server::dto::responses::{GameStateForPlayer, OpponentState, PlayerProfile};
+use crate::server::dto::responses::{GameStateForPlayer, GameStateForSpectator, OpponentState, PlayerProfile};
 
 
 pub const MAX_PLAYERS: usize = 6;
@@ -18,22 +22,72 @@ pub const INITIAL_HAND_AMOUNT: usize = 5;
 pub const DRAW_CARD_LIMIT: usize = 5;   // can't draw if player has more than / or this amount of cards
 pub const TURN_DURATION: Duration = Duration::from_secs(90);
 
+/// RNG used for every random event of a game (shuffles, dice, random cards)
+pub type GameRng = StdRng;
 
-#[derive(Debug)]
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
 pub enum Order {
     Forward,
     Backward
 }
 
-#[derive(Debug)]
+/// Neighbours of a player, based on the turn order.
+/// The player on the right plays after the player, the player on the left plays before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Neighbour {
+    Left,
+    Right
+}
+
+#[derive(Debug, Clone)]
 pub enum GameState {
     PreGame,
     InGame,
     EndGame { winner_id: PlayerId }
 }
 
+/// Event happening while the turn changes
+#[derive(Debug, Clone)]
+pub enum TurnEvent {
+    /// the player skips their turn because of the debuff
+    Skipped { player_id: PlayerId, reason: BuffVariant },
+    /// periodic buffs of the player applied at the start or end of their turn
+    Tick { player_id: PlayerId, tick: TickTime, action: PlayAction },
+}
+
+/// Card played for a player by auto_play_card
+#[derive(Debug, Clone)]
+pub struct AutoPlay {
+    pub targets: Vec<PlayerId>,
+    /// default choice used if the card needed one
+    pub choice: Option<Choice>,
+    pub play_info: PlayInfo,
+}
+
+/// Values given by a card to the card it plays (nested plays and replays)
+#[derive(Debug, Clone)]
+pub struct PlayContext {
+    /// buffs of the player used by the previous plays, UntilUsed buffs in this set don't apply again
+    pub buffs_used: HashSet<usize>,
+    /// dice roll given to the modifiers of the card, rolled by the card that plays it
+    pub dice_roll: Option<u8>,
+    /// multiplier of the attack of the card, applied after the buffs of the player
+    pub attack_mul: u32,
+}
+
+impl Default for PlayContext {
+    fn default() -> Self {
+        Self {
+            buffs_used: HashSet::new(),
+            dice_roll: None,
+            attack_mul: 1,
+        }
+    }
+}
+
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Game {
     pub players: Vec<Player>,
     pub player_profiles: Vec<PlayerProfile>,
@@ -44,24 +98,49 @@ pub struct Game {
     /// Is determined when playing a card, and reset when the turn is advanced.
     pub estimated_turn_end_offset: Duration,
     pub turn_order: Order,
-    pub state: GameState
+    pub state: GameState,
+    /// Choice made by the current player for the card being played.
+    /// Only set while a card is played.
+    pub choice: Option<Choice>,
+    /// Context of the card being played by another card.
+    /// Only set while a card is played by another card.
+    pub play_context: PlayContext,
+    /// Card played by the current player, waiting for a choice of the player
+    pub pending_choice: Option<PendingChoice>,
+    /// Elemental affinities applied to the damage of the cards (no affinity if None)
+    pub affinities: Option<AffinityTable>,
+    /// Seed of `rng`, a game with the same seed, cards and actions has the same outcome
+    pub seed: u64,
+    pub rng: GameRng,
 }
 
 impl Game {
+    /// Create a game with a random seed
     pub fn new(player_profiles: &Vec<PlayerProfile>, cards: Vec<Box<dyn Card>>) -> Self {
+        Self::with_seed(player_profiles, cards, rand::random())
+    }
+
+    /// Create a game with a fixed seed
+    pub fn with_seed(player_profiles: &[PlayerProfile], cards: Vec<Box<dyn Card>>, seed: u64) -> Self {
         let players = player_profiles.iter()
             .map(|prf| Player::new(prf.id, prf.name.clone()))
             .collect();
 
         Self {
             players: players,
-            player_profiles: player_profiles.clone(),
+            player_profiles: player_profiles.to_vec(),
             pile: cards,
             current_player_turn: 0,
             current_player_turn_end: Utc::now(),
             estimated_turn_end_offset: Duration::ZERO,
             turn_order: Order::Forward,
             state: GameState::PreGame,
+            choice: None,
+            play_context: PlayContext::default(),
+            pending_choice: None,
+            affinities: None,
+            seed,
+            rng: GameRng::seed_from_u64(seed),
         }
     }
 
@@ -93,8 +172,21 @@ impl Game {
     }
 
     pub fn shuffle_pile(&mut self) {
-        let mut rng = rng();
-        self.pile.shuffle(&mut rng);
+        self.pile.shuffle(&mut self.rng);
+    }
+
+    /// Roll a dice with the RNG of the game
+    pub fn roll_dice(&mut self) -> u8 {
+        self.rng.random_range(0..6) + 1
+    }
+
+    /// Dice roll given to the modifiers of the card being played.
+    /// Uses the dice roll of the play context if the card that plays the card already rolled it.
+    pub fn roll_card_dice(&mut self) -> u8 {
+        match self.play_context.dice_roll.take() {
+            Some(dice_roll) => dice_roll,
+            None => self.roll_dice()
+        }
     }
 
     pub fn collect_discard_cards(&mut self) {
@@ -104,41 +196,199 @@ impl Game {
         }
     }
 
+    /// Collect the discard piles in the pile and shuffle it if the pile has less than `amount` cards.
+    /// Returns true if the discard piles were collected.
+    pub fn refill_pile(&mut self, amount: usize) -> bool {
+        if self.pile.len() >= amount {
+            return false;
+        }
+
+        self.collect_discard_cards();
+        self.shuffle_pile();
+        true
+    }
+
+    /// Indices of all the players in turn order, starting with `first_index`
+    pub fn players_in_turn_order(&self, first_index: usize) -> Vec<usize> {
+        let mut indices = Vec::with_capacity(self.players.len());
+        let mut index = first_index;
+        for _ in 0..self.players.len() {
+            indices.push(index);
+            index = self.neighbour_index(index, Neighbour::Right);
+        }
+
+        indices
+    }
+
     pub fn current_player_id(&self) -> PlayerId {
         self.players.get(self.current_player_turn).unwrap().id
     }
 
     pub fn next_player_index(&self) -> usize {
-        match self.turn_order {
-            Order::Forward => {
-                if self.current_player_turn + 1 >= self.players.len() {
-                    0
-                } else {
-                    self.current_player_turn + 1
-                }
-            },
-            Order::Backward => {
-                if self.current_player_turn as i32 - 1 < 0 {
-                    self.players.len() - 1
-                } else {
-                    self.current_player_turn - 1
-                }
+        self.neighbour_index(self.current_player_turn, Neighbour::Right)
+    }
+
+    /// Index of the neighbour of a player, using the seat index of the players and the turn order
+    pub fn neighbour_index(&self, player_index: usize, neighbour: Neighbour) -> usize {
+        let forward = matches!((self.turn_order, neighbour), (Order::Forward, Neighbour::Right) | (Order::Backward, Neighbour::Left));
+
+        if forward {
+            if player_index + 1 >= self.players.len() {
+                0
+            } else {
+                player_index + 1
+            }
+        } else {
+            if player_index as i32 - 1 < 0 {
+                self.players.len() - 1
+            } else {
+                player_index - 1
             }
         }
     }
 
-    pub fn advance_turn(&mut self) {
+    /// Put the discard pile of a player on top of the discard pile of another player.
+    /// Returns the ids of the moved cards.
+    pub fn move_discard_pile(&mut self, from_index: usize, to_index: usize) -> Vec<CardId> {
+        if from_index == to_index {
+            return Vec::new();
+        }
+
+        let mut cards = std::mem::take(&mut self.players[from_index].discard_cards);
+        let card_ids = cards.iter().map(|c| c.get_id()).collect();
+        self.players[to_index].discard_cards.append(&mut cards);
+
+        card_ids
+    }
+
+    /// Exchange a card in the hand of a player with the last card of the discard pile of another player.
+    /// Returns the ids of the given and taken cards, or None if the discard pile is empty.
+    pub fn swap_hand_with_discard(&mut self, player_index: usize, hand_index: usize, discard_index: usize) -> Option<(CardId, CardId)> {
+        if hand_index >= self.players[player_index].hand_cards.len() {
+            return None;
+        }
+
+        let taken = self.players[discard_index].discard_cards.pop()?;
+        let given = self.players[player_index].hand_cards.remove(hand_index);
+        let ids = (given.get_id(), taken.get_id());
+
+        self.players[player_index].hand_cards.insert(hand_index, taken);
+        self.players[discard_index].discard_cards.push(given);
+
+        Some(ids)
+    }
+
+    pub fn reverse_turn_order(&mut self) {
+        self.turn_order = match self.turn_order {
+            Order::Forward => Order::Backward,
+            Order::Backward => Order::Forward,
+        };
+    }
+
+    /// Go to the next player's turn.
+    /// The periodic buffs of the players tick at the end of the current turn and at the start of the next turns.
+    /// Players with a SkipTurn debuff are skipped (the debuff is removed), their turn starts and ends like the other turns.
+    /// Returns the events of the turn change in order. The turn does not change if a tick ends the game.
+    pub fn advance_turn(&mut self) -> Vec<TurnEvent> {
+        let mut events = Vec::new();
+        self.pending_choice = None;
+
+        if self.end_player_turn(&mut events) {
+            return events;
+        }
+
         self.current_player_turn = self.next_player_index();
+        if self.start_player_turn(&mut events) {
+            return events;
+        }
+
+        // a player can't be skipped more than once per turn change
+        for _ in 0..self.players.len() {
+            let player = &mut self.players[self.current_player_turn];
+
+            match player.buffs.iter().position(|b| b.is_active() && b.get_type() == BuffType::SkipTurn) {
+                Some(idx) => {
+                    let debuff = player.buffs.remove(idx);
+                    events.push(TurnEvent::Skipped { player_id: player.id, reason: debuff.as_variant() });
+
+                    if self.end_player_turn(&mut events) {
+                        return events;
+                    }
+
+                    self.current_player_turn = self.next_player_index();
+                    if self.start_player_turn(&mut events) {
+                        return events;
+                    }
+                }
+                None => break
+            }
+        }
+
         self.current_player_turn_end = Utc::now() + TURN_DURATION + self.estimated_turn_end_offset;
         // reset
         self.estimated_turn_end_offset = Duration::ZERO;
+
+        events
+    }
+
+    /// Tick the buffs of the current player at the start of their turn, and count the turn in the lifetime of their buffs.
+    /// Returns true if a tick ends the game.
+    fn start_player_turn(&mut self, events: &mut Vec<TurnEvent>) -> bool {
+        self.tick_player_buffs(self.current_player_turn, TickTime::TurnStart, events);
+        if self.check_game_end() {
+            return true;
+        }
+
+        self.players[self.current_player_turn].advance_buffs_turn();
+        false
+    }
+
+    /// Tick the buffs of the current player at the end of their turn.
+    /// Returns true if a tick ends the game.
+    fn end_player_turn(&mut self, events: &mut Vec<TurnEvent>) -> bool {
+        self.tick_player_buffs(self.current_player_turn, TickTime::TurnEnd, events);
+        self.check_game_end()
+    }
+
+    fn tick_player_buffs(&mut self, player_index: usize, tick: TickTime, events: &mut Vec<TurnEvent>) {
+        let targets = self.players[player_index].tick_buffs(tick);
+
+        if !targets.is_empty() {
+            let mut action = PlayAction::new();
+            action.targets = targets;
+            self.estimated_turn_end_offset += action.get_estimated_time();
+            events.push(TurnEvent::Tick { player_id: self.players[player_index].id, tick, action });
+        }
+    }
+
+    /// Set the state to EndGame if one player or less is alive (state change will be checked by server to send game end event with the winner).
+    /// Returns true if the game is over.
+    fn check_game_end(&mut self) -> bool {
+        let remaining_players: Vec<&Player> = self.players.iter()
+            .filter(|p| p.health > 0)
+            .collect();
+
+        if remaining_players.len() == 1 {
+            self.state = GameState::EndGame { winner_id: remaining_players[0].id };
+        } else if remaining_players.is_empty() {
+            self.state = GameState::EndGame { winner_id: self.current_player_id() };
+        }
+
+        matches!(self.state, GameState::EndGame { .. })
     }
 
+    /// Play a card of the player.
+    /// If the card needs a choice of the player, the card is not played:
+    /// it is kept in `pending_choice` until `resolve_choice()` is called and an empty PlayInfo is returned.
     pub fn play_card(&mut self, player_id: PlayerId, card_index: usize, targets: Vec<PlayerId>) -> Result<PlayInfo, String> {
         if self.current_player_id() != player_id {
             return Err("Not player's current turn".to_string());
         }
 
+        if self.pending_choice.is_some() {
+            return Err("Waiting for a choice".to_string());
+        }
+
         let player_index = self.players
             .iter()
             .position(|p| p.id == player_id)
@@ -162,31 +412,68 @@ impl Game {
         let card = self.players[player_index].hand_cards.get(card_index)
             .ok_or_else(|| "Card not in hand".to_string())?.clone();
 
+        if let Some((request, default)) = card.get_choice_request(player_index, card_index, &target_indices, self) {
+            // check the targets now, the player should not have to make a choice for a card that can't be played
+            card.validate_targets(&target_indices.iter().map(|i| &self.players[*i]).collect())?;
+
+            self.pending_choice = Some(PendingChoice {
+                player_id,
+                card_index,
+                targets: target_indices.iter().map(|&i| self.players[i].id).collect(),
+                request,
+                default,
+            });
+            return Ok(PlayInfo::new());
+        }
+
+        self.resolve_play(player_index, card_index, target_indices, None)
+    }
+
+    /// Answer the pending choice of the player and play the card.
+    /// The default choice of the card is used if `choice` is None.
+    pub fn resolve_choice(&mut self, player_id: PlayerId, choice: Option<Choice>) -> Result<PlayInfo, String> {
+        let pending = self.pending_choice.as_ref()
+            .filter(|pending| pending.player_id == player_id)
+            .ok_or_else(|| "No choice to make".to_string())?;
+
+        let player_index = self.players
+            .iter()
+            .position(|p| p.id == player_id)
+            .ok_or_else(|| "Player not found".to_string())?;
+
+        let choice = choice.unwrap_or_else(|| pending.default.clone());
+        pending.request.validate(&choice, &self.players[player_index], pending.card_index)?;
+
+        // unwrap: checked above
+        let pending = self.pending_choice.take().unwrap();
+        let target_indices = pending.targets.iter()
+            .filter_map(|id| self.players.iter().position(|p| p.id == *id))
+            .collect();
+
+        self.resolve_play(player_index, pending.card_index, target_indices, Some(choice))
+    }
+
+    fn resolve_play(&mut self, player_index: usize, card_index: usize, target_indices: Vec<usize>, choice: Option<Choice>) -> Result<PlayInfo, String> {
+        let card = self.players[player_index].hand_cards.get(card_index)
+            .ok_or_else(|| "Card not in hand".to_string())?.clone();
+        let card_id = card.get_id();
+
         // play the card and return play info
-        match card.play(player_index, target_indices, self) {
-            Ok((play_info, buffs_used)) => {
+        self.choice = choice;
+        let result = card.play(player_index, target_indices, self);
+        self.choice = None;
+
+        match result {
+            Ok((mut play_info, buffs_used)) => {
+                self.apply_reflected_damage(player_index, &mut play_info);
+
                 // remove used buffs
                 self.remove_player_buffs_used(player_index, buffs_used);
 
-                let card = self.players[player_index].hand_cards.remove(card_index);
-                // grant card buffs to player
-                for buff in card.get_buffs() {
-                    self.players[player_index].buffs.push(buff);
-                }
-                // remove card from hand and put it in discard pile
-                self.players[player_index].discard_cards.push(card);
+                self.finish_play(player_index, card_id, &play_info)?;
 
                 // check if game is over
-                let remaining_players: Vec<&Player> = self.players.iter()
-                    .filter(|p| p.health > 0)
-                    .collect();
-
-                // state change will be checked by server to send game end event with the winner
-                if remaining_players.len() == 1 {
-                    self.state = GameState::EndGame { winner_id: remaining_players[0].id };
-                } else if remaining_players.len() == 0 {
-                    self.state = GameState::EndGame { winner_id: self.current_player_id() };
-                }
+                self.check_game_end();
 
                 self.estimated_turn_end_offset += play_info.get_estimated_time();
 
@@ -196,6 +483,193 @@ impl Game {
         }
     }
 
+    /// Deal the damage reflected by the targets of the card back to the player
+    fn apply_reflected_damage(&mut self, player_index: usize, play_info: &mut PlayInfo) {
+        let player_id = self.players[player_index].id;
+        let mut reflect_action = PlayAction::new();
+
+        for target in play_info.actions.iter().flat_map(|action| action.targets.iter()) {
+            match target.action {
+                // damage the player dealt to themselves is not reflected
+                ActionType::Attack { reflected, .. } if reflected > 0 && target.player_id != player_id => {
+                    let action_target = self.players[player_index].damage_unreflected(reflected, target.effect.clone());
+                    reflect_action.targets.push(action_target);
+                }
+                _ => {}
+            }
+        }
+
+        if !reflect_action.targets.is_empty() {
+            play_info.actions.push(reflect_action);
+        }
+    }
+
+    /// Move the played card from the hand of the player to the discard pile (or back in the hand, or to another player) and grant its buffs
+    fn finish_play(&mut self, player_index: usize, card_id: CardId, play_info: &PlayInfo) -> Result<(), String> {
+        // the hand can change while playing the card (stolen or given cards), find it back by id
+        let card_index = self.players[player_index].hand_cards.iter()
+            .position(|c| c.get_id() == card_id)
+            .ok_or_else(|| "Card not in hand".to_string())?;
+        let card = self.players[player_index].hand_cards.remove(card_index);
+        // grant card buffs to player
+        for mut buff in card.get_buffs() {
+            // counted in turns so that the buff is not used by the next cards played in this turn
+            if buff.get_lifetime() == BuffLifeTime::UntilNextTurnEnd {
+                buff.set_lifetime(BuffLifeTime::Turns { delay: 1, turns: 1 });
+            }
+            self.players[player_index].buffs.push(buff);
+        }
+        let receiver_index = play_info.give_to.and_then(|id| self.players.iter().position(|p| p.id == id));
+        if play_info.return_to_hand {
+            // put the card back at the end of the hand
+            self.players[player_index].hand_cards.push(card);
+        } else if let Some(receiver_index) = receiver_index {
+            self.players[receiver_index].hand_cards.push(card);
+        } else {
+            // remove card from hand and put it in discard pile
+            self.players[player_index].discard_cards.push(card);
+        }
+
+        Ok(())
+    }
+
+    /// Play a card while another card of the player is being played (the actions are added to the PlayInfo of that card).
+    /// The card is put in the hand of the player, and stays there if it can't be played on the targets.
+    /// The attack of the card is multiplied by `attack_mul` (applied after the buffs of the player).
+    /// The UntilUsed buffs in `buffs_used` don't apply to the card, the buffs used by the card are added to `buffs_used`.
+    /// The end of the game is not checked, it is checked when the first card is resolved.
+    pub fn play_nested_card(&mut self, player_index: usize, card: Box<dyn Card>, target_indices: &[usize], attack_mul: u32, buffs_used: &mut HashSet<usize>) -> Result<Option<PlayInfo>, String> {
+        let card_id = card.get_id();
+        self.players[player_index].hand_cards.push(card.clone());
+
+        // use the targets of the first card if possible
+        let candidates = vec![target_indices.to_vec(), target_indices.iter().take(1).cloned().collect(), Vec::new()];
+        let target_indices = match candidates.into_iter()
+            .find(|indices| card.validate_targets(&indices.iter().map(|i| &self.players[*i]).collect()).is_ok()) {
+            Some(indices) => indices,
+            None => { return Ok(None); }
+        };
+
+        // choices are not asked for nested cards, the default choice is used
+        let hand_index = self.players[player_index].hand_cards.len() - 1;
+        let choice = card.get_choice_request(player_index, hand_index, &target_indices, self).map(|(_, default)| default);
+        // the buffs used by the first cards don't apply twice
+        let context = PlayContext {
+            buffs_used: self.play_context.buffs_used.union(buffs_used).cloned().collect(),
+            dice_roll: None,
+            attack_mul,
+        };
+
+        let first_choice = std::mem::replace(&mut self.choice, choice);
+        let first_context = std::mem::replace(&mut self.play_context, context);
+        let result = card.play(player_index, target_indices, self);
+        self.choice = first_choice;
+        self.play_context = first_context;
+
+        let (play_info, nested_buffs_used) = result?;
+        buffs_used.extend(nested_buffs_used);
+
+        self.finish_play(player_index, card_id, &play_info)?;
+
+        Ok(Some(play_info))
+    }
+
+    /// Check if the current player can play more cards in this turn (the turn is not ended after a card is played).
+    /// A player with an active PlayAllCards buff can play several cards until the turn is ended.
+    pub fn allows_more_plays(&self) -> bool {
+        if !matches!(self.state, GameState::InGame) || self.pending_choice.is_some() {
+            return false;
+        }
+
+        let player = &self.players[self.current_player_turn];
+        !player.hand_cards.is_empty()
+            && player.buffs.iter().any(|b| b.is_active() && matches!(b.get_type(), BuffType::PlayAllCards { .. }))
+    }
+
+    /// Check if the player can end a turn that allows more plays.
+    /// All the cards required by PlayAllCards buffs must have been played.
+    pub fn end_turn(&self, player_id: PlayerId) -> Result<(), String> {
+        if self.current_player_id() != player_id {
+            return Err("Not player's current turn".to_string());
+        }
+
+        if self.pending_choice.is_some() {
+            return Err("Waiting for a choice".to_string());
+        }
+
+        if !self.allows_more_plays() {
+            return Err("Turn can't be ended".to_string());
+        }
+
+        self.check_required_cards_played(player_id)
+    }
+
+    /// Damage percentage of a card of the element on the player
+    pub fn get_affinity(&self, element: Element, player_index: usize) -> u32 {
+        match &self.affinities {
+            Some(affinities) => affinities.get_multiplier(element, &self.players[player_index]),
+            None => NEUTRAL_AFFINITY
+        }
+    }
+
+    /// Choose the element of the player for the elemental affinity, the player can only choose it during their turn
+    pub fn choose_element(&mut self, player_id: PlayerId, element: Element) -> Result<(), String> {
+        if !matches!(&self.affinities, Some(affinities) if affinities.source == AffinitySource::Chosen) {
+            return Err("Elements can't be chosen".to_string());
+        }
+
+        if self.current_player_id() != player_id {
+            return Err("Not player's current turn".to_string());
+        }
+
+        let player = self.players
+            .iter_mut()
+            .find(|p| p.id == player_id)
+            .ok_or_else(|| "Player not found".to_string())?;
+
+        player.element = Some(element);
+        Ok(())
+    }
+
+    /// Check if the player played all the cards required by PlayAllCards buffs (the turn can end)
+    pub fn check_required_cards_played(&self, player_id: PlayerId) -> Result<(), String> {
+        let player = self.players
+            .iter()
+            .find(|p| p.id == player_id)
+            .ok_or_else(|| "Player not found".to_string())?;
+
+        if !player.required_cards().is_empty() {
+            Err("Cards must be played before the end of the turn".to_string())
+        } else { Ok(()) }
+    }
+
+    /// Play a card of the player with the first valid targets, and the default choice if the card needs one.
+    /// Used to play the cards required by PlayAllCards buffs when the turn ends.
+    pub fn auto_play_card(&mut self, player_id: PlayerId, card_index: usize) -> Result<AutoPlay, String> {
+        let opponents: Vec<PlayerId> = self.players.iter()
+            .filter(|p| p.id != player_id && p.health > 0)
+            .map(|p| p.id)
+            .collect();
+
+        let mut result = Err("No valid targets".to_string());
+        let mut targets = Vec::new();
+        for count in 0..=opponents.len() {
+            targets = opponents[..count].to_vec();
+            result = self.play_card(player_id, card_index, targets.clone());
+            if result.is_ok() {
+                break;
+            }
+        }
+
+        let mut choice = None;
+        if let (Ok(_), Some(pending)) = (&result, &self.pending_choice) {
+            choice = Some(pending.default.clone());
+            result = self.resolve_choice(player_id, None);
+        }
+
+        result.map(|play_info| AutoPlay { targets, choice, play_info })
+    }
+
     // there should always be at least 1 card in pile when called
     pub fn draw_card(&mut self, player_id: PlayerId) -> Result<CardId, String> {
         let player_index = self.players
@@ -203,6 +677,10 @@ impl Game {
             .position(|p| p.id == player_id)
             .ok_or_else(|| "Player not found".to_string())?;
 
+        if self.pending_choice.is_some() {
+            return Err("Waiting for a choice".to_string());
+        }
+
         if self.players[player_index].hand_cards.len() >= DRAW_CARD_LIMIT {
             return Err("Player can't draw more cards".to_string());
         }
@@ -233,6 +711,10 @@ impl Game {
                         buffs_to_remove.push(idx);
                     }
                 }
+                // removed when the turn changes
+                BuffLifeTime::Turns { .. } => {}
+                // removed once all the ticks are applied
+                BuffLifeTime::Ticks { .. } => {}
             }
         }
 
@@ -254,17 +736,7 @@ impl Game {
 
         let opp_states = self.players.iter()
             .filter(|player| player.id != player_id)
-            .map(|opp| OpponentState {
-                player_id: opp.id,
-                health: opp.health as u32,
-                card_count: opp.hand_cards.len() as u32,
-                discard_cards: opp.discard_cards.iter()
-                    .map(|card| card.get_id())
-                    .collect(),
-                buffs: opp.buffs.iter()
-                    .map(|b| b.as_variant())
-                    .collect()
-            })
+            .map(Self::public_player_state)
             .collect();
 
         Ok(GameStateForPlayer {
@@ -278,11 +750,211 @@ impl Game {
                 .map(|card| card.get_id())
                 .collect(),
             buffs: player.buffs.iter()
-                .map(|b| b.as_variant())
+                .map(|b| b.as_status())
                 .collect(),
+            element: player.element,
             opponents: opp_states,
             cards_in_pile: self.pile.len() as u32
         })
     }
+
+    /// Game status without the cards in hand of the players
+    pub fn status_for_spectator(&self) -> GameStateForSpectator {
+        GameStateForSpectator {
+            current_player_turn: self.current_player_id(),
+            current_player_turn_end: self.current_player_turn_end,
+            players: self.players.iter()
+                .map(Self::public_player_state)
+                .collect(),
+            cards_in_pile: self.pile.len() as u32
+        }
+    }
+
+    /// State of a player visible by the other players
+    fn public_player_state(player: &Player) -> OpponentState {
+        OpponentState {
+            player_id: player.id,
+            health: player.health as u32,
+            card_count: player.hand_cards.len() as u32,
+            discard_cards: player.discard_cards.iter()
+                .map(|card| card.get_id())
+                .collect(),
+            buffs: player.buffs.iter()
+                .map(|b| b.as_status())
+                .collect(),
+            element: player.element,
+        }
+    }
+}
+
+
+
+/// Game factories shared by the tests of the game modules
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use super::*;
+    use crate::server::game::card_info::CardInfoList;
+
+    /// Game with `players` players and an empty pile
+    pub(crate) fn make_game(players: i32) -> Game {
+        let profiles: Vec<PlayerProfile> = (1..=players)
+            .map(|id| PlayerProfile { id, name: format!("Player {}", id) })
+            .collect();
+        Game::with_seed(&profiles, Vec::new(), 0)
+    }
+
+    /// Started game with 3 players and 30 cards
+    pub(crate) fn make_seeded_game(seed: u64) -> Game {
+        let cards = (0..30).map(|i| format!(r#"{{"name":"Card {}","element":"Fire","stars":"One","kind":"Spell","type":"BasicCard","attack":1}}"#, i))
+            .collect::<Vec<String>>()
+            .join(",");
+        let cards = serde_json::from_str::<CardInfoList>(&format!("[{}]", cards)).unwrap().0.iter()
+            .map(|info| info.make_card())
+            .collect();
+
+        let profiles: Vec<PlayerProfile> = (1..=3)
+            .map(|id| PlayerProfile { id, name: format!("Player {}", id) })
+            .collect();
+        let mut game = Game::with_seed(&profiles, cards, seed);
+        game.begin();
+        game
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_utils::{make_game, make_seeded_game};
+    use crate::server::game::buffs::{AttackBuff, DamageOverTimeDebuff, DotKind, SkipTurnDebuff};
+    use crate::server::game::card_info::CardInfo;
+    use crate::server::game::eval::EvalOp;
+
+    /// BasicCard with the fields of `data`
+    fn make_card(data: &str) -> Box<dyn Card> {
+        let json = format!(r#"{{"name":"Test","element":"Fire","stars":"One","kind":"Spell","type":"BasicCard",{}}}"#, data);
+        serde_json::from_str::<CardInfo>(&json).unwrap().make_card()
+    }
+
+    #[test]
+    fn nested_card_attack_is_multiplied() {
+        let mut game = make_game(2);
+        let health = game.players[1].health;
+        game.pile.push(make_card(r#""attack":3"#));
+        game.players[0].hand_cards.push(make_card(r#""attack":1,"nested_play":{"source":"Pile","attack_mul":2}"#));
+        game.players[0].buffs.push(Box::new(AttackBuff {
+            value: 1,
+            op: EvalOp::Add,
+            elements: Vec::new(),
+            kinds: Vec::new(),
+            stars: Vec::new(),
+            lifetime: BuffLifeTime::UntilUsed,
+        }));
+
+        game.play_card(1, 0, vec![2]).unwrap();
+
+        // (1 + 1) for the first card, 3 * 2 for the nested card (the buff is already used)
+        assert_eq!(game.players[1].health, health - 8);
+        assert!(game.players[0].buffs.is_empty());
+        assert_eq!(game.players[0].discard_cards.len(), 2);
+    }
+
+    #[test]
+    fn draw_card_draws_for_targets() {
+        let mut game = make_game(2);
+        game.pile.push(make_card(r#""attack":1"#));
+        game.pile.push(make_card(r#""attack":1"#));
+        game.players[0].hand_cards.push(make_card(r#""draw":1,"draw_scope":"Targets""#));
+
+        assert!(game.play_card(1, 0, Vec::new()).is_err());
+        game.play_card(1, 0, vec![2]).unwrap();
+
+        assert_eq!(game.players[0].hand_cards.len(), 0);
+        assert_eq!(game.players[1].hand_cards.len(), 1);
+    }
+
+    #[test]
+    fn nested_card_is_drawn_after_refill() {
+        let mut game = make_game(2);
+        let health = game.players[1].health;
+        // the pile is empty, the card comes from the collected discard piles
+        game.players[1].discard_cards.push(make_card(r#""attack":3"#));
+        game.players[0].hand_cards.push(make_card(r#""attack":1,"nested_play":{"source":"Pile"}"#));
+
+        let info = game.play_card(1, 0, vec![2]).unwrap();
+
+        assert_eq!(game.players[1].health, health - 4);
+        assert!(info.actions.iter().flat_map(|a| a.targets.iter()).any(|t| matches!(t.action, ActionType::CollectDiscardCards { .. })));
+    }
+
+    #[test]
+    fn dead_players_do_not_discard() {
+        let mut game = make_game(3);
+        game.players[2].health = 0;
+        for (i, player) in game.players.iter_mut().enumerate() {
+            player.hand_cards.push(make_card(&format!(r#""id":{},"attack":1"#, i + 1)));
+        }
+        game.players[0].hand_cards.push(make_card(r#""id":10,"attack":1,"discard":{"scope":"All"}"#));
+
+        game.play_card(1, 1, vec![2]).unwrap();
+
+        assert_eq!(game.players[1].discard_cards.len(), 1);
+        assert!(game.players[2].discard_cards.is_empty());
+        assert_eq!(game.players[2].hand_cards.len(), 1);
+    }
+
+    #[test]
+    fn unmatched_nested_card_is_drawn() {
+        let mut game = make_game(2);
+        let health = game.players[1].health;
+        // Fire card, only Water cards are played
+        game.pile.push(make_card(r#""attack":3"#));
+        game.players[0].hand_cards.push(make_card(r#""attack":1,"nested_play":{"source":"Pile","filter":{"elements":["Water"]}}"#));
+
+        let info = game.play_card(1, 0, vec![2]).unwrap();
+
+        assert_eq!(game.players[1].health, health - 1);
+        assert_eq!(game.players[0].hand_cards.len(), 1);
+        assert!(game.pile.is_empty());
+        assert_eq!(info.actions.iter().flat_map(|a| a.targets.iter()).filter(|t| matches!(t.action, ActionType::Draw { .. })).count(), 1);
+    }
+
+    fn card_ids(cards: &Vec<Box<dyn Card>>) -> Vec<CardId> {
+        cards.iter().map(|c| c.get_id()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let mut first = make_seeded_game(42);
+        let mut second = make_seeded_game(42);
+
+        assert_eq!(card_ids(&first.pile), card_ids(&second.pile));
+        for (a, b) in first.players.iter().zip(second.players.iter()) {
+            assert_eq!(card_ids(&a.hand_cards), card_ids(&b.hand_cards));
+        }
+        let first_rolls: Vec<u8> = (0..20).map(|_| first.roll_dice()).collect();
+        let second_rolls: Vec<u8> = (0..20).map(|_| second.roll_dice()).collect();
+        assert_eq!(first_rolls, second_rolls);
+
+        let other = make_seeded_game(43);
+        assert_ne!(card_ids(&first.pile), card_ids(&other.pile));
+    }
+
+    #[test]
+    fn skipped_turns_end_like_other_turns() {
+        let mut game = make_game(3);
+        let health = game.players[1].health;
+        game.players[1].buffs.push(Box::new(SkipTurnDebuff));
+        game.players[1].buffs.push(Box::new(DamageOverTimeDebuff {
+            amount: 2,
+            kind: DotKind::default(),
+            tick: TickTime::TurnEnd,
+            lifetime: BuffLifeTime::Turns { delay: 0, turns: 3 },
+        }));
+
+        let events = game.advance_turn();
+
+        assert_eq!(game.current_player_turn, 2);
+        assert_eq!(game.players[1].health, health - 2);
+        assert!(matches!(events[..], [TurnEvent::Skipped { player_id: 2, .. }, TurnEvent::Tick { player_id: 2, tick: TickTime::TurnEnd, .. }]));
+    }
+}
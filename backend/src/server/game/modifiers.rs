@@ -1,13 +1,21 @@
 use std::{fmt::Debug};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 
-use super::{cards::card::Element, eval::EvalOp, player::{Player, PlayerId}};
+use super::{cards::card::Element, eval::EvalOp, game::GameRng, player::{Player, PlayerId}};
 
 
 pub trait Modifier: Sync + Send + Debug + ModifierClone {
     /// Return tuple with new value + dice roll (if used) + player id (if used).
     /// Target is not used for heal and draw.
-    fn compute(&self, base_value: u32, player: &Player, target: &Player, dice_roll: Option<u8>) -> (u32, u8, PlayerId);
+    /// Dice that are not given are rolled with `rng` (the RNG of the game).
+    fn compute(&self, base_value: u32, player: &Player, target: &Player, dice_roll: Option<u8>, rng: &mut GameRng) -> (u32, u8, PlayerId);
+
+    /// Same as compute(), but returns every dice roll made by the modifier (empty if no dice roll is used).
+    fn compute_rolls(&self, base_value: u32, player: &Player, target: &Player, dice_roll: Option<u8>, rng: &mut GameRng) -> (u32, Vec<u8>, PlayerId) {
+        let (result, dice_roll, player_dice_id) = self.compute(base_value, player, target, dice_roll, rng);
+        (result, if dice_roll > 0 { vec![dice_roll] } else { Vec::new() }, player_dice_id)
+    }
 }
 
 // Allow Box<dyn Modifier> clonning
@@ -41,6 +49,7 @@ pub enum ModifierInfo {
     DiscardSizeModifier(DiscardSizeModifier),
     HandAndDiceModifier(HandAndDiceModifier),
     HandElementsCountModifier(HandElementsCountModifier),
+    DiceExprModifier(DiceExprModifier),
 }
 
 impl ModifierInfo {
@@ -51,6 +60,7 @@ impl ModifierInfo {
             ModifierInfo::DiscardSizeModifier(m) => Box::new(m),
             ModifierInfo::HandAndDiceModifier(m) => Box::new(m),
             ModifierInfo::HandElementsCountModifier(m) => Box::new(m),
+            ModifierInfo::DiceExprModifier(m) => Box::new(m),
         }
     }
 }
@@ -72,8 +82,8 @@ pub struct DiceRollModifier {
 }
 
 impl Modifier for DiceRollModifier {
-    fn compute(&self, base_value: u32, player: &Player, target: &Player, dice_roll: Option<u8>) -> (u32, u8, PlayerId) {
-        let dice_roll: u8 = dice_roll.unwrap_or_else(|| rand::random_range(0..6) + 1);
+    fn compute(&self, base_value: u32, player: &Player, target: &Player, dice_roll: Option<u8>, rng: &mut GameRng) -> (u32, u8, PlayerId) {
+        let dice_roll: u8 = dice_roll.unwrap_or_else(|| rng.random_range(0..6) + 1);
         let mut result: u32 = self.dice_op.eval(base_value, dice_roll as u32);
         // cap result
         if result > self.cap { result = self.cap; }
@@ -98,7 +108,7 @@ pub struct HandSizeModifier {
 }
 
 impl Modifier for HandSizeModifier {
-    fn compute(&self, base_value: u32, player: &Player, target: &Player, _dice_roll: Option<u8>) -> (u32, u8, PlayerId) {
+    fn compute(&self, base_value: u32, player: &Player, target: &Player, _dice_roll: Option<u8>, _rng: &mut GameRng) -> (u32, u8, PlayerId) {
         let hand_size = if self.from_target { target.hand_cards.len() } else { player.hand_cards.len() } as u32;
         let mut result: u32 = self.hand_size_op.eval(base_value, hand_size);
         // cap result
@@ -124,7 +134,7 @@ pub struct DiscardSizeModifier {
 }
 
 impl Modifier for DiscardSizeModifier {
-    fn compute(&self, base_value: u32, player: &Player, target: &Player, _dice_roll: Option<u8>) -> (u32, u8, PlayerId) {
+    fn compute(&self, base_value: u32, player: &Player, target: &Player, _dice_roll: Option<u8>, _rng: &mut GameRng) -> (u32, u8, PlayerId) {
         let discard_size = if self.from_target { target.discard_cards.len() } else { player.discard_cards.len() } as u32;
         let mut result: u32 = self.discard_size_op.eval(base_value, discard_size);
         // cap result
@@ -153,8 +163,8 @@ pub struct HandAndDiceModifier {
 }
 
 impl Modifier for HandAndDiceModifier {
-    fn compute(&self, _base_value: u32, player: &Player, target: &Player, dice_roll: Option<u8>) -> (u32, u8, PlayerId) {
-        let dice_roll: u8 = dice_roll.unwrap_or_else(|| rand::random_range(0..6) + 1);
+    fn compute(&self, _base_value: u32, player: &Player, target: &Player, dice_roll: Option<u8>, rng: &mut GameRng) -> (u32, u8, PlayerId) {
+        let dice_roll: u8 = dice_roll.unwrap_or_else(|| rng.random_range(0..6) + 1);
         let hand_size = if self.hand_from_target { target.hand_cards.len() } else { player.hand_cards.len() } as u32;
         let mut result: u32 = self.op.eval(hand_size, dice_roll as u32);
         // cap result
@@ -182,7 +192,7 @@ pub struct HandElementsCountModifier {
 }
 
 impl Modifier for HandElementsCountModifier {
-    fn compute(&self, base_value: u32, player: &Player, target: &Player, _dice_roll: Option<u8>) -> (u32, u8, PlayerId) {
+    fn compute(&self, base_value: u32, player: &Player, target: &Player, _dice_roll: Option<u8>, _rng: &mut GameRng) -> (u32, u8, PlayerId) {
         let hand = if self.from_target { &target.hand_cards } else { &player.hand_cards };
         let count = hand.iter().filter(|c| c.get_element() == self.element).count() as u32;
         let mut result: u32 = self.op.eval(base_value, count);
@@ -194,3 +204,74 @@ impl Modifier for HandElementsCountModifier {
         (result, 0, -1)
     }
 }
+
+
+/// Expression rolling several dice
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum DiceExpr {
+    /// roll `count` dice and add the rolls
+    Sum { count: u32 },
+    /// roll `count` dice and multiply the rolls
+    Product { count: u32 },
+    /// roll a dice, then roll as many dice as the first roll and add all the rolls
+    RollThenSum,
+}
+
+impl DiceExpr {
+    /// Returns the result of the expression and the dice rolls
+    pub fn roll(&self, rng: &mut GameRng) -> (u32, Vec<u8>) {
+        let mut roll = || -> u8 { rng.random_range(0..6) + 1 };
+
+        match self {
+            DiceExpr::Sum { count } => {
+                let rolls: Vec<u8> = (0..*count).map(|_| roll()).collect();
+                (rolls.iter().map(|&r| r as u32).sum(), rolls)
+            }
+            DiceExpr::Product { count } => {
+                let rolls: Vec<u8> = (0..*count).map(|_| roll()).collect();
+                (rolls.iter().map(|&r| r as u32).product(), rolls)
+            }
+            DiceExpr::RollThenSum => {
+                let first = roll();
+                let rolls: Vec<u8> = std::iter::once(first)
+                    .chain((0..first).map(|_| roll()))
+                    .collect();
+                (rolls.iter().map(|&r| r as u32).sum(), rolls)
+            }
+        }
+    }
+}
+
+
+fn default_dice_expr_op() -> EvalOp { EvalOp::Add }
+
+/// Modifier using the result of a dice expression. The dice roll of the card is not used.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub struct DiceExprModifier {
+    pub expr: DiceExpr,
+    #[serde(default = "default_dice_expr_op")]
+    pub op: EvalOp,
+    /// maximum value
+    #[serde(default = "default_cap")]
+    pub cap: u32,
+}
+
+impl Modifier for DiceExprModifier {
+    fn compute(&self, base_value: u32, player: &Player, target: &Player, dice_roll: Option<u8>, rng: &mut GameRng) -> (u32, u8, PlayerId) {
+        let (result, dice_rolls, player_dice_id) = self.compute_rolls(base_value, player, target, dice_roll, rng);
+        (result, dice_rolls.first().copied().unwrap_or(0), player_dice_id)
+    }
+
+    fn compute_rolls(&self, base_value: u32, player: &Player, _target: &Player, _dice_roll: Option<u8>, rng: &mut GameRng) -> (u32, Vec<u8>, PlayerId) {
+        let (value, dice_rolls) = self.expr.roll(rng);
+        let mut result: u32 = self.op.eval(base_value, value);
+        // cap result
+        if result > self.cap { result = self.cap; }
+
+        println!("DiceExprModifier: base_value={}, dice_rolls={:?}, result={}", base_value, dice_rolls, result);
+
+        (result, dice_rolls, player.id)
+    }
+}
@@ -2,6 +2,7 @@ use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
+use super::buffs::BuffVariant;
 use super::cards::card::CardId;
 use super::player::PlayerId;
 
@@ -9,6 +10,9 @@ use super::player::PlayerId;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayAction {
     pub dice_roll : u8,
+    /// every dice roll if several dice are rolled (`dice_roll` is the first one)
+    #[serde(default)]
+    pub dice_rolls: Vec<u8>,
     pub player_dice_id: PlayerId,
     pub targets: Vec<ActionTarget>,
 }
@@ -22,16 +26,48 @@ pub struct ActionTarget {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayInfo {
-    pub actions: Vec<PlayAction>
+    pub actions: Vec<PlayAction>,
+    /// if true, the played card goes back to the player's hand instead of the discard pile
+    #[serde(default)]
+    pub return_to_hand: bool,
+    /// player receiving the played card in their hand instead of the discard pile
+    #[serde(default)]
+    pub give_to: Option<PlayerId>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag="type")]
 pub enum ActionType {
-    Attack{amount: u32},
+    /// `amount` is the damage taken by the target, `raw` the damage before the elemental affinity and the defensive buffs of the target,
+    /// `affinity` the damage percentage from the elemental affinity (100 if neutral), `reflected` the damage dealt back to the attacker
+    Attack{amount: u32, raw: u32, affinity: u32, reflected: u32},
     Heal{amount: u32},
     Draw{cards: Vec<CardId>},
     Discard{cards: Vec<usize>},
+    /// Cards drawn by the target from the discard pile of player `from`
+    DrawDiscard{from: PlayerId, cards: Vec<CardId>},
+    /// Cards taken from the target's hand and given to player `to`
+    Steal{to: PlayerId, cards: Vec<CardId>},
+    /// Cards given to the target by player `from`
+    Give{from: PlayerId, cards: Vec<CardId>},
+    /// Debuff given to the target
+    Debuff{buff: BuffVariant},
+    /// Buff given to the target by the card they played
+    Buff{buff: BuffVariant},
+    /// Buffs removed from the target
+    Dispel{buffs: Vec<BuffVariant>},
+    /// Cards in the target's hand shown to player `to`
+    Reveal{to: PlayerId, cards: Vec<CardId>},
+    /// Card played by the target while playing another card, its actions follow
+    Play{card: CardId},
+    /// Played card given by the target to player `to` (in their hand)
+    PassCard{to: PlayerId, card: CardId},
+    /// Discard pile of the target put on top of the discard pile of player `to`
+    MoveDiscardPile{to: PlayerId, cards: Vec<CardId>},
+    /// Discard piles of all the players put back in the pile (shuffled) so that the target can draw
+    CollectDiscardCards{cards_in_pile: u32},
+    /// Card `given` from the target's hand exchanged with the card `taken` from the discard pile of player `with`
+    SwapWithDiscard{with: PlayerId, given: CardId, taken: CardId},
 }
 
 
@@ -45,16 +81,63 @@ impl ActionType {
 }
 
 
+impl ActionTarget {
+    /// Returns a copy of the action target where the card ids are replaced by -1
+    /// if they should not be visible to the viewer.
+    pub fn redacted_for(&self, viewer_id: PlayerId) -> ActionTarget {
+        let hide = |cards: &Vec<CardId>, visible: bool| -> Vec<CardId> {
+            if visible { cards.clone() } else { vec![-1; cards.len()] }
+        };
+
+        let action = match &self.action {
+            ActionType::Draw { cards } => {
+                ActionType::Draw { cards: hide(cards, viewer_id == self.player_id) }
+            }
+            ActionType::Steal { to, cards } => {
+                ActionType::Steal { to: *to, cards: hide(cards, viewer_id == self.player_id || viewer_id == *to) }
+            }
+            ActionType::Give { from, cards } => {
+                ActionType::Give { from: *from, cards: hide(cards, viewer_id == self.player_id || viewer_id == *from) }
+            }
+            ActionType::Reveal { to, cards } => {
+                ActionType::Reveal { to: *to, cards: hide(cards, viewer_id == *to) }
+            }
+            action => action.clone()
+        };
+
+        ActionTarget { player_id: self.player_id, action, effect: self.effect.clone() }
+    }
+}
+
+
 impl PlayAction {
     pub fn new() -> Self {
-        Self { dice_roll: 0, player_dice_id: -1, targets: Vec::new() }
+        Self { dice_roll: 0, dice_rolls: Vec::new(), player_dice_id: -1, targets: Vec::new() }
+    }
+
+    /// Show the dice rolls made by a modifier on the client.
+    /// A single roll is the dice roll of the card, which is only shown the first time it is used.
+    pub fn show_dice_rolls(&mut self, dice_rolls: Vec<u8>, player_dice_id: PlayerId, dice_roll_used: &mut bool) {
+        if player_dice_id == -1 || dice_rolls.is_empty() {
+            return;
+        }
+
+        if dice_rolls.len() > 1 {
+            self.dice_roll = dice_rolls[0];
+            self.dice_rolls = dice_rolls;
+            self.player_dice_id = player_dice_id;
+        } else if !*dice_roll_used {
+            self.dice_roll = dice_rolls[0];
+            self.player_dice_id = player_dice_id;
+            *dice_roll_used = true;
+        }
     }
 
     pub fn get_estimated_time(&self) -> Duration {
         let time = {
             // if dice roll is used
             if self.dice_roll > 0 {
-                Duration::from_millis(120 * 6 + 1000)
+                Duration::from_millis(120 * 6 + 1000) * self.dice_rolls.len().max(1) as u32
             } else { Duration::ZERO }
         };
 
@@ -62,11 +145,22 @@ impl PlayAction {
             .map(|target| target.action.get_estimated_time())
             .sum::<Duration>()
     }
+
+    pub fn redacted_for(&self, viewer_id: PlayerId) -> PlayAction {
+        PlayAction {
+            dice_roll: self.dice_roll,
+            dice_rolls: self.dice_rolls.clone(),
+            player_dice_id: self.player_dice_id,
+            targets: self.targets.iter()
+                .map(|target| target.redacted_for(viewer_id))
+                .collect()
+        }
+    }
 }
 
 impl PlayInfo {
     pub fn new() -> Self {
-        Self { actions: Vec::new() }
+        Self { actions: Vec::new(), return_to_hand: false, give_to: None }
     }
 
     /// Estimated amount of time that it will take for the web app to show the actions to the user.
@@ -75,4 +169,15 @@ impl PlayInfo {
             .map(|action| action.get_estimated_time())
             .sum()
     }
+
+    /// Card ids are only visible to the players that receive or lose them
+    pub fn redacted_for(&self, viewer_id: PlayerId) -> PlayInfo {
+        PlayInfo {
+            actions: self.actions.iter()
+                .map(|action| action.redacted_for(viewer_id))
+                .collect(),
+            return_to_hand: self.return_to_hand,
+            give_to: self.give_to,
+        }
+    }
 }
\ No newline at end of file
@@ -1,13 +1,17 @@
-use crate::server::game::buffs::Buff;
+use rand::seq::SliceRandom;
 
-use super::{cards::card::{Card, EffectId}, play_info::{ActionTarget, ActionType}};
+use crate::server::game::affinity::NEUTRAL_AFFINITY;
+use crate::server::game::buffs::{Buff, BuffKind, BuffLifeTime, BuffType, Dispel, DotKind, TickTime};
+
+use super::game::GameRng;
+use super::{cards::card::{Card, CardFilter, CardId, EffectId, Element}, play_info::{ActionTarget, ActionType}};
 
 const PLAYER_MAX_HEALTH: i32 = 100;
 
 
 pub type PlayerId = i32;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Player {
     pub id: PlayerId,
     pub name: String,
@@ -15,6 +19,8 @@ pub struct Player {
     pub hand_cards: Vec<Box<dyn Card>>,
     pub discard_cards: Vec<Box<dyn Card>>,
     pub buffs: Vec<Box<dyn Buff>>,
+    /// element chosen by the player, used for the elemental affinity of the damage they take
+    pub element: Option<Element>,
 }
 
 impl PartialEq for Player {
@@ -31,19 +37,63 @@ impl Player {
             hand_cards: Vec::new(),
             discard_cards: Vec::new(),
             buffs: Vec::new(),
+            element: None,
         }
     }
 
-    pub fn damage(&mut self, amount: u32, effect: EffectId) -> ActionTarget {
-        let effective_damage = amount as i32;
+    /// Damage the player. The damage is multiplied by the elemental `affinity` (percentage),
+    /// then reduced by the active defensive buffs of the player (depleted buffs are removed).
+    pub fn damage(&mut self, amount: u32, affinity: u32, effect: EffectId) -> ActionTarget {
+        self.take_damage(amount, affinity, effect, true)
+    }
+
+    /// Damage that can't be reflected (damage dealt back by a Reflect buff, damage over time), without elemental affinity
+    pub fn damage_unreflected(&mut self, amount: u32, effect: EffectId) -> ActionTarget {
+        self.take_damage(amount, NEUTRAL_AFFINITY, effect, false)
+    }
+
+    fn take_damage(&mut self, raw_amount: u32, affinity: u32, effect: EffectId, can_reflect: bool) -> ActionTarget {
+        let amount = raw_amount * affinity / 100;
+        let mut effective_damage = amount;
+        let mut depleted: Vec<usize> = Vec::new();
+
+        // immunity first to keep the shields, then reductions before the shields absorb the rest
+        for kind in [BuffKind::Immunity, BuffKind::DamageReduction, BuffKind::Shield] {
+            for (idx, buff) in self.buffs.iter_mut().enumerate() {
+                if effective_damage == 0 {
+                    break;
+                }
+
+                if buff.is_active() && buff.get_type().get_kind() == kind {
+                    let (remaining, is_depleted) = buff.mitigate(effective_damage);
+                    effective_damage = remaining;
+                    if is_depleted {
+                        depleted.push(idx);
+                    }
+                }
+            }
+        }
+
+        depleted.sort();
+        for &idx in depleted.iter().rev() {
+            self.buffs.remove(idx);
+        }
 
-        if self.health - effective_damage < 0 {
+        let reflected = if can_reflect {
+            let percent: u32 = self.buffs.iter()
+                .filter(|b| b.is_active())
+                .map(|b| match b.get_type() { BuffType::Reflect { percent } => percent, _ => 0 })
+                .sum();
+            amount * percent / 100
+        } else { 0 };
+
+        if self.health - (effective_damage as i32) < 0 {
             self.health = 0;
         } else {
-            self.health -= effective_damage;
+            self.health -= effective_damage as i32;
         }
-        
-        ActionTarget { player_id: self.id, action: ActionType::Attack{ amount: effective_damage as u32 }, effect }
+
+        ActionTarget { player_id: self.id, action: ActionType::Attack{ amount: effective_damage, raw: raw_amount, affinity, reflected }, effect }
     }
 
     pub fn heal(&mut self, amount: u32, effect: EffectId) -> ActionTarget {
@@ -57,4 +107,232 @@ impl Player {
         ActionTarget { player_id: self.id, action: ActionType::Heal { amount: effective_heal as u32 }, effect }
     }
 
+    /// Apply the periodic buffs of the player (damage over time, regeneration) that tick at this moment of the turn.
+    /// Buffs with a Ticks lifetime are removed once they applied all their ticks.
+    pub fn tick_buffs(&mut self, tick: TickTime) -> Vec<ActionTarget> {
+        // dead players are not affected
+        if self.health <= 0 {
+            return Vec::new();
+        }
+
+        let mut ticks: Vec<(BuffType, EffectId)> = Vec::new();
+        let mut expired: Vec<usize> = Vec::new();
+
+        for (idx, buff) in self.buffs.iter_mut().enumerate() {
+            if !buff.is_active() {
+                continue;
+            }
+
+            let effect = match buff.get_type() {
+                BuffType::DamageOverTime { kind, tick: buff_tick, .. } if buff_tick == tick => {
+                    match kind {
+                        DotKind::Burn => EffectId::from("damage_burn"),
+                        DotKind::Poison => EffectId::from("damage_poison"),
+                    }
+                }
+                BuffType::Regeneration { tick: buff_tick, .. } if buff_tick == tick => EffectId::from("heal_regeneration"),
+                _ => { continue; }
+            };
+            ticks.push((buff.get_type(), effect));
+
+            if let BuffLifeTime::Ticks { ticks } = buff.get_lifetime() {
+                if ticks > 1 {
+                    buff.set_lifetime(BuffLifeTime::Ticks { ticks: ticks - 1 });
+                } else {
+                    expired.push(idx);
+                }
+            }
+        }
+
+        // remove before applying the ticks, damage can remove depleted defensive buffs
+        for &idx in expired.iter().rev() {
+            self.buffs.remove(idx);
+        }
+
+        ticks.into_iter()
+            .filter_map(|(buff_type, effect)| match buff_type {
+                BuffType::DamageOverTime { amount, .. } => Some(self.damage_unreflected(amount, effect)),
+                BuffType::Regeneration { amount, .. } => Some(self.heal(amount, effect)),
+                _ => None
+            })
+            .collect()
+    }
+
+    /// Remove random cards matching the filter from the hand.
+    /// If amount is None, all matching cards are removed.
+    /// The card with the excluded id (usually the card being played) is never removed.
+    pub fn take_hand_cards(&mut self, filter: &CardFilter, amount: Option<u32>, excluded: Option<CardId>, rng: &mut GameRng) -> Vec<Box<dyn Card>> {
+        let indices = self.select_hand_cards(filter, false, amount, excluded, rng);
+
+        let mut cards: Vec<Box<dyn Card>> = indices.iter()
+            .map(|&idx| self.hand_cards.remove(idx))
+            .collect();
+        // keep the order of the hand
+        cards.reverse();
+
+        cards
+    }
+
+    /// Update the lifetime of the buffs at the start of a turn of the player.
+    /// Expired buffs are removed.
+    pub fn advance_buffs_turn(&mut self) {
+        let mut expired: Vec<usize> = Vec::new();
+
+        for (idx, buff) in self.buffs.iter_mut().enumerate() {
+            if let BuffLifeTime::Turns { delay, turns } = buff.get_lifetime() {
+                if delay > 0 {
+                    buff.set_lifetime(BuffLifeTime::Turns { delay: delay - 1, turns });
+                } else if turns > 1 {
+                    buff.set_lifetime(BuffLifeTime::Turns { delay, turns: turns - 1 });
+                } else {
+                    expired.push(idx);
+                }
+            }
+        }
+
+        for &idx in expired.iter().rev() {
+            self.buffs.remove(idx);
+        }
+    }
+
+    /// Hand indices of the cards the player has to play before the end of the turn (active PlayAllCards buffs).
+    pub fn required_cards(&self) -> Vec<usize> {
+        let buffs: Vec<&Box<dyn Buff>> = self.buffs.iter()
+            .filter(|b| b.is_active() && matches!(b.get_type(), BuffType::PlayAllCards { .. }))
+            .collect();
+
+        self.hand_cards.iter()
+            .enumerate()
+            .filter(|(_, card)| buffs.iter().any(|b| b.is_applicable(card.get_element(), card.get_kind(), card.get_stars())))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Remove the buffs selected by the dispel.
+    /// Returns the removed buffs.
+    pub fn dispel_buffs(&mut self, dispel: &Dispel) -> Vec<Box<dyn Buff>> {
+        let (removed, kept) = self.buffs.drain(..)
+            .partition(|b| dispel.is_matching(b.as_ref()));
+        self.buffs = kept;
+
+        removed
+    }
+
+    /// Move the cards matching the filter (or not matching it if `exclude` is true) from the hand to the discard pile.
+    /// If amount is set, only that many random cards among them are discarded.
+    /// The card with the excluded id (usually the card being played) is never discarded.
+    /// Returns the hand indices of the discarded cards in descending order.
+    pub fn discard_hand_cards(&mut self, filter: &CardFilter, exclude: bool, amount: Option<u32>, excluded: Option<CardId>, rng: &mut GameRng) -> Vec<usize> {
+        let indices = self.select_hand_cards(filter, exclude, amount, excluded, rng);
+
+        for &idx in indices.iter() {
+            let card = self.hand_cards.remove(idx);
+            self.discard_cards.push(card);
+        }
+
+        indices
+    }
+
+    /// Hand indices of the cards matching the filter (or not matching it if `exclude` is true), in descending order.
+    /// If amount is set, only that many random cards among them are selected.
+    /// The card with the excluded id is never selected.
+    fn select_hand_cards(&self, filter: &CardFilter, exclude: bool, amount: Option<u32>, excluded: Option<CardId>, rng: &mut GameRng) -> Vec<usize> {
+        let mut indices: Vec<usize> = self.hand_cards.iter().enumerate()
+            .filter(|(_, card)| Some(card.get_id()) != excluded && filter.is_matching(card.as_ref()) != exclude)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        if let Some(amount) = amount {
+            indices.shuffle(rng);
+            indices.truncate(amount as usize);
+        }
+
+        // remove from the end to keep indices valid
+        indices.sort();
+        indices.reverse();
+
+        indices
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::game::buffs::{DamageReductionBuff, ImmunityBuff, ReflectBuff, ShieldBuff};
+
+    const LIFETIME: BuffLifeTime = BuffLifeTime::Turns { delay: 0, turns: 1 };
+
+    /// (amount, raw, affinity, reflected) of an attack
+    fn attack_values(target: &ActionTarget) -> (u32, u32, u32, u32) {
+        match target.action {
+            ActionType::Attack { amount, raw, affinity, reflected } => (amount, raw, affinity, reflected),
+            _ => panic!("not an attack")
+        }
+    }
+
+    fn make_player(buffs: Vec<Box<dyn Buff>>) -> Player {
+        let mut player = Player::new(1, String::from("Player"));
+        player.buffs = buffs;
+        player
+    }
+
+    #[test]
+    fn reductions_apply_before_shields() {
+        let mut player = make_player(vec![
+            Box::new(ShieldBuff { points: 5, lifetime: LIFETIME }),
+            Box::new(DamageReductionBuff { value: 4, percent: false, lifetime: LIFETIME }),
+        ]);
+
+        let target = player.damage(10, NEUTRAL_AFFINITY, EffectId::new());
+
+        // 10 - 4, then the shield absorbs 5 and is depleted
+        assert_eq!(attack_values(&target).0, 1);
+        assert_eq!(player.health, PLAYER_MAX_HEALTH - 1);
+        assert_eq!(player.buffs.len(), 1);
+        assert_eq!(player.buffs[0].get_type().get_kind(), BuffKind::DamageReduction);
+    }
+
+    #[test]
+    fn immunity_keeps_shields() {
+        let mut player = make_player(vec![
+            Box::new(ShieldBuff { points: 5, lifetime: LIFETIME }),
+            Box::new(ImmunityBuff { lifetime: LIFETIME }),
+        ]);
+
+        let target = player.damage(10, NEUTRAL_AFFINITY, EffectId::new());
+
+        assert_eq!(attack_values(&target).0, 0);
+        assert_eq!(player.buffs.len(), 1);
+        assert_eq!(player.buffs[0].get_type(), BuffType::Shield { points: 5 });
+    }
+
+    #[test]
+    fn affinity_applies_before_mitigation() {
+        let mut player = make_player(vec![
+            Box::new(DamageReductionBuff { value: 50, percent: true, lifetime: LIFETIME }),
+        ]);
+
+        let target = player.damage(10, 200, EffectId::new());
+
+        // 10 * 200%, then halved
+        assert_eq!(attack_values(&target), (10, 10, 200, 0));
+        assert_eq!(player.health, PLAYER_MAX_HEALTH - 10);
+    }
+
+    #[test]
+    fn reflect_uses_damage_before_mitigation() {
+        let mut player = make_player(vec![
+            Box::new(ShieldBuff { points: 100, lifetime: LIFETIME }),
+            Box::new(ReflectBuff { percent: 50, lifetime: LIFETIME }),
+        ]);
+
+        let target = player.damage(10, 200, EffectId::new());
+        // the shield absorbs the damage, half of the damage after the affinity is reflected
+        assert_eq!(attack_values(&target), (0, 10, 200, 10));
+
+        let target = player.damage_unreflected(10, EffectId::new());
+        assert_eq!(attack_values(&target).3, 0);
+    }
 }
@@ -12,7 +12,7 @@ use tokio::{sync::mpsc, time::interval};
 
 use crate::server::game::player::PlayerId;
 
-use super::{dto::{actions::UserAction, responses::ServerResponse}, server::{ConnId, GameServerHandle}};
+use super::{dto::{actions::UserAction, responses::{PlayerProfile, Seq, ServerResponse, PROTOCOL_VERSION}}, server::{ConnId, GameServerHandle}};
 
 /// How often heartbeat pings are sent
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
@@ -22,11 +22,15 @@ const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Echo text & binary messages received from the client, respond to ping messages, and monitor
 /// connection health to detect network issues and free up resources.
+/// `spectator` is the profile of the user if they watch the game without playing.
+/// `last_seq` is the sequence number of the last response received by a player that reconnects.
 pub async fn game_ws(
     game_server: GameServerHandle,
     mut session: actix_ws::Session,
     msg_stream: actix_ws::MessageStream,
     player_id: i32,
+    spectator: Option<PlayerProfile>,
+    last_seq: Option<Seq>,
 ) {
     log::info!("New session connected");
 
@@ -35,8 +39,13 @@ pub async fn game_ws(
 
     let (conn_tx, mut conn_rx) = mpsc::unbounded_channel();
 
+    let is_spectator = spectator.is_some();
+
     // connect: if connection with this player_id exists, replace it
-    let conn_id = game_server.connect(player_id, conn_tx).await;
+    let conn_id = match spectator {
+        Some(profile) => game_server.connect_spectator(profile, conn_tx).await,
+        None => game_server.connect(player_id, last_seq, conn_tx).await,
+    };
 
     let msg_stream = msg_stream
         .max_frame_size(128 * 1024)     // ~1Mb
@@ -48,8 +57,16 @@ pub async fn game_ws(
     let player_id: PlayerId = player_id;
 
     // Send session info
-    ServerResponse::SessionInfo { id: player_id, players: game_server.get_session_info().await }
-        .send(&mut session).await.unwrap();
+    let session_info = ServerResponse::SessionInfo {
+        protocol_version: PROTOCOL_VERSION,
+        id: player_id,
+        players: game_server.get_session_info().await,
+        spectators: game_server.get_spectators().await
+    };
+    if session_info.send(&mut session).await.is_err() {
+        game_server.disconnect(conn_id);
+        return;
+    }
 
     // begin loop
 
@@ -80,7 +97,7 @@ pub async fn game_ws(
                     }
 
                     AggregatedMessage::Text(text) => {
-                        let close_reason = process_received_text(&game_server, &mut session, &text, conn_id, player_id).await;
+                        let close_reason = process_received_text(&game_server, &mut session, &text, conn_id, player_id, is_spectator).await;
                         // break if process_received_text returned a close reason
                         if close_reason.is_some() {
                             break close_reason;
@@ -105,15 +122,12 @@ pub async fn game_ws(
             Either::Left((Either::Left((None, _)), _)) => break None,
 
             // messages received from other handlers or server
-            // send ServerResponse back to the client
+            // send ServerResponse back to the client, already serialized by the server
             Either::Left((Either::Right((Some(json_msg), _)), _)) => {
-                let possible_response: Result<ServerResponse, _> = from_str(&json_msg);
-                match possible_response {
-                    Ok(resp) => {
-                        println!("Sending response (player_id: {}): {:?}", player_id, resp);
-                        resp.send(&mut session).await.unwrap();
-                    }
-                    Err(_) => { panic!("Invalid ServerResponse received !\nraw response: {}", json_msg) }
+                println!("Sending response (player_id: {}): {}", player_id, json_msg);
+                if session.text(json_msg).await.is_err() {
+                    // session closed
+                    break None;
                 }
                 // log::info!("chat_msg: {chat_msg:?}");
                 // // session.text(serde_json::to_string(&chat_message).unwrap()).await.unwrap();
@@ -154,24 +168,50 @@ pub async fn game_ws(
     let _ = session.close(close_reason).await;
 }
 
-/// Process received user data
+/// Process received user data.
+/// Spectators can only send chat messages.
 async fn process_received_text(
     game_server: &GameServerHandle,
     _session: &mut actix_ws::Session,
     text: &str,
     conn: ConnId,
     player_id: PlayerId,
+    is_spectator: bool,
 ) -> Option<CloseReason> {
     let json_str = text.trim();
 
     let possible_action: Result<UserAction, _> = from_str(&json_str);
 
     match possible_action {
+        Ok(UserAction::SendChatMessage { message }) if is_spectator => {
+            log::info!("Send Spectator Chat Message Action: message: {message:?}");
+            game_server.send_message(conn, message).await;
+        },
+
+        Ok(action) if is_spectator => {
+            log::warn!("Spectator {player_id} can't play: {action:?}");
+        },
+
         Ok(UserAction::PlayCard { card_index, targets }) => {
             log::info!("Play Card Action: index: {card_index:?}, targets: {targets:?}");
             let _ = game_server.send_play_card_action(player_id, card_index, targets).await;
         },
 
+        Ok(UserAction::ResolveChoice { choice }) => {
+            log::info!("Resolve Choice Action: choice: {choice:?}");
+            let _ = game_server.send_resolve_choice_action(player_id, choice).await;
+        },
+
+        Ok(UserAction::EndTurn {  }) => {
+            log::info!("End Turn Action");
+            let _ = game_server.send_end_turn_action(player_id).await;
+        },
+
+        Ok(UserAction::ChooseElement { element }) => {
+            log::info!("Choose Element Action: element: {element:?}");
+            let _ = game_server.send_choose_element_action(player_id, element).await;
+        },
+
         Ok(UserAction::DrawCard {  }) => {
             log::info!("Draw Card Action");
             let _ = game_server.send_draw_card_action(player_id).await;
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::GameId;
+
+use super::dto::actions::UserAction;
+use super::dto::responses::{PlayerProfile, ServerResponse};
+use super::game::cards::card::CardId;
+use super::game::game::{Game, GameState};
+use super::game::player::PlayerId;
+
+
+/// Who made a move recorded in the replay
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+pub enum MoveAuthor {
+    /// action sent by the player
+    Player,
+    /// move of a bot playing for the player
+    Bot,
+    /// move made by the server when the time of the turn ran out
+    /// (default choice, cards required by PlayAllCards buffs, end of the turn)
+    Server,
+}
+
+/// Move accepted by the server, with the turn it was played in
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ReplayAction {
+    pub turn: u32,
+    /// player the move was made for
+    pub player_id: PlayerId,
+    pub author: MoveAuthor,
+    #[schema(value_type = Object)]
+    pub action: UserAction,
+}
+
+/// Response broadcasted to the players, with the turn it was sent in.
+/// Hidden information (drawn cards, revealed hands...) is not removed.
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct ReplayEvent {
+    pub turn: u32,
+    #[schema(value_type = Object)]
+    pub response: ServerResponse,
+}
+
+
+/// Record of a game, stored in the "replays" collection when the game is over
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct Replay {
+    pub game_id: String,
+    /// seed of the game RNG, as a string because it does not fit in a JSON number (or a BSON integer)
+    pub seed: String,
+    pub players: Vec<PlayerProfile>,
+    /// order of the pile once shuffled at the beginning of the game.
+    /// The first cards are dealt to the players, by seat order.
+    pub deck: Vec<CardId>,
+    /// number of turns played (the first turn starts with the game, each ChangeTurn starts a new turn)
+    pub turn_count: u32,
+    /// None if the game was stopped before its end
+    pub winner_id: Option<PlayerId>,
+    pub actions: Vec<ReplayAction>,
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    /// Start the record of a game that just began
+    pub fn new(game_id: GameId, game: &Game) -> Self {
+        let deck = game.players.iter()
+            .flat_map(|player| player.hand_cards.iter())
+            .chain(game.pile.iter())
+            .map(|card| card.get_id())
+            .collect();
+
+        Self {
+            game_id: game_id.to_string(),
+            seed: game.seed.to_string(),
+            players: game.player_profiles.clone(),
+            deck,
+            turn_count: 1,
+            winner_id: None,
+            actions: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Index of the turn being played
+    pub fn current_turn(&self) -> u32 {
+        self.turn_count - 1
+    }
+
+    pub fn record_action(&mut self, player_id: PlayerId, author: MoveAuthor, action: UserAction) {
+        self.actions.push(ReplayAction { turn: self.current_turn(), player_id, author, action });
+    }
+
+    pub fn record_event(&mut self, response: &ServerResponse) {
+        if let ServerResponse::ChangeTurn { .. } = response {
+            self.turn_count += 1;
+        }
+        self.events.push(ReplayEvent { turn: self.current_turn(), response: response.clone() });
+    }
+
+    /// Set the winner once the game is over
+    pub fn finish(&mut self, game: &Game) {
+        if let GameState::EndGame { winner_id } = game.state {
+            self.winner_id = Some(winner_id);
+        }
+    }
+
+    /// Actions and events of a turn, None if the turn was not played
+    pub fn turn(&self, turn: u32) -> Option<ReplayTurn> {
+        if turn >= self.turn_count {
+            return None;
+        }
+
+        Some(ReplayTurn {
+            turn,
+            turn_count: self.turn_count,
+            actions: self.actions.iter().filter(|a| a.turn == turn).cloned().collect(),
+            events: self.events.iter().filter(|e| e.turn == turn).cloned().collect(),
+        })
+    }
+}
+
+
+/// Part of a replay played in a single turn
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReplayTurn {
+    pub turn: u32,
+    pub turn_count: u32,
+    pub actions: Vec<ReplayAction>,
+    pub events: Vec<ReplayEvent>,
+}
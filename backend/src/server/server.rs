@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap, io, pin::pin, time::Duration
+    collections::HashMap, io, pin::pin, time::{Duration, Instant}
 };
 
 use futures::lock::Mutex;
@@ -9,10 +9,11 @@ use futures_util::{
 };
 
 use chrono::Utc;
+use rand::{Rng, SeedableRng};
 use tokio::{sync::{mpsc, oneshot}, time::interval};
 use uid::IdU64;
 
-use crate::{backend_db::BackendDb, server::{dto::responses::ServerResponse, game::{cards::card::CardId, game::{GameState, DRAW_CARD_LIMIT}, play_info::PlayInfo}}, GameId};
+use crate::{backend_db::BackendDb, server::{dto::{actions::UserAction, responses::{Seq, ServerResponse}}, events::EventBuffer, replay::{MoveAuthor, Replay}, game::{affinity::load_affinity_table, bot::{BotDifficulty, BotMove}, cards::card::{CardId, Element}, choice::Choice, game::{GameRng, GameState, Order, TurnEvent, DRAW_CARD_LIMIT}, play_info::{ActionType, PlayInfo}}}, GameId};
 
 use super::{dto::responses::PlayerProfile, game::{game::Game, player::PlayerId}};
 
@@ -24,12 +25,26 @@ pub type Msg = String;
 
 const TURN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
+/// Time before a bot plays for a player who left the game
+const TAKEOVER_DELAY: Duration = Duration::from_secs(20);
+/// Difficulty of the bot playing for a player who left the game
+const TAKEOVER_DIFFICULTY: BotDifficulty = BotDifficulty::Greedy;
+
 
 /// A command received by the [`GameServer`] (sent by a [`GameServerHandle`])
 #[derive(Debug)]
 enum Command {
     Connect {
         player_id: PlayerId,
+        /// sequence number of the last response received by the client, if it reconnects
+        last_seq: Option<Seq>,
+        conn_tx: mpsc::UnboundedSender<Msg>,
+        res_tx: oneshot::Sender<ConnId>,
+    },
+
+    /// connect a user who watches the game without playing
+    ConnectSpectator {
+        profile: PlayerProfile,
         conn_tx: mpsc::UnboundedSender<Msg>,
         res_tx: oneshot::Sender<ConnId>,
     },
@@ -42,6 +57,10 @@ enum Command {
         res_tx: oneshot::Sender<Vec<PlayerProfile>>,
     },
 
+    SpectatorList {
+        res_tx: oneshot::Sender<Vec<PlayerProfile>>,
+    },
+
     /// send game state to client
     GameStateForPlayer {
         player_id: PlayerId,
@@ -55,6 +74,23 @@ enum Command {
         res_tx: oneshot::Sender<Result<PlayInfo, String>>,
     },
 
+    ResolveChoice {
+        player_id: PlayerId,
+        choice: Choice,
+        res_tx: oneshot::Sender<Result<PlayInfo, String>>,
+    },
+
+    EndTurn {
+        player_id: PlayerId,
+        res_tx: oneshot::Sender<Result<(), String>>,
+    },
+
+    ChooseElement {
+        player_id: PlayerId,
+        element: Element,
+        res_tx: oneshot::Sender<Result<(), String>>,
+    },
+
     DrawCard {
         player_id: PlayerId,
         res_tx: oneshot::Sender<Result<CardId, String>>,
@@ -73,10 +109,66 @@ enum Command {
 
 
 /// Map of connection IDs to the player id and their message receivers.
+/// Spectators are in `sessions` too (with their account id), to receive the broadcasted responses.
 /// Intended to be wrapped in a Mutex.
 #[derive(Debug)]
 pub struct SessionsInner {
     sessions: HashMap<ConnId, (PlayerId, mpsc::UnboundedSender<Msg>)>,
+    /// connections of the spectators
+    spectators: HashMap<ConnId, PlayerProfile>,
+    /// players of the game, their responses are kept in `events` even when they are disconnected
+    players: Vec<PlayerId>,
+    /// last responses sent, for the players that reconnect
+    events: EventBuffer,
+}
+
+impl SessionsInner {
+    /// Send the same response to all the sessions
+    fn broadcast(&mut self, resp: &ServerResponse) {
+        self.send_each(|_, _, _| Some(resp.clone()));
+    }
+
+    /// Send the response returned by `f` for each receiver (connection if connected, player id, is spectator).
+    /// All the responses share the same sequence number.
+    fn send_each(&mut self, f: impl Fn(Option<ConnId>, PlayerId, bool) -> Option<ServerResponse>) {
+        let seq = self.events.next_seq();
+
+        for &player_id in self.players.iter() {
+            let conn = self.sessions.iter().find(|(_, (id, _))| *id == player_id);
+            if let Some(resp) = f(conn.map(|(conn_id, _)| *conn_id), player_id, false) {
+                self.events.push(seq, player_id, conn.map(|(_, (_, tx))| tx), resp);
+            }
+        }
+
+        for (conn_id, profile) in self.spectators.iter() {
+            if let Some((_, tx)) = self.sessions.get(conn_id) {
+                if let Some(resp) = f(Some(*conn_id), profile.id, true) {
+                    self.events.push(seq, profile.id, Some(tx), resp);
+                }
+            }
+        }
+    }
+
+    /// Send a response to a single player or spectator
+    fn send_to(&mut self, player_id: PlayerId, resp: ServerResponse) {
+        self.send_each(|_, id, _| if id == player_id { Some(resp.clone()) } else { None });
+    }
+
+    /// Send again the responses missed by a player.
+    /// Returns false if they are no longer available.
+    fn resend_missed_events(&self, player_id: PlayerId, last_seq: Seq) -> bool {
+        match self.events.missed_events(player_id, last_seq) {
+            Some(msgs) => {
+                if let Some((_, (_, tx))) = self.sessions.iter().find(|(_, (id, _))| *id == player_id) {
+                    for msg in msgs {
+                        let _ = tx.send(msg);
+                    }
+                }
+                true
+            }
+            None => false
+        }
+    }
 }
 
 
@@ -99,120 +191,529 @@ pub struct GameServer {
 
     /// sent when run is called
     ready_tx: Option<oneshot::Sender<()>>,
+
+    /// record of the game, started when the game begins and saved when it ends (or when the server stops)
+    replay: Option<Replay>,
+
+    /// players controlled by the server
+    bots: HashMap<PlayerId, BotDifficulty>,
+
+    /// players that are not connected, with the time they left (a bot plays for them after TAKEOVER_DELAY)
+    disconnected: HashMap<PlayerId, Instant>,
+
+    /// RNG of the bots, separate from the game RNG so that the moves of the bots don't change the game rolls
+    bot_rng: GameRng,
 }
 
 impl GameServer {
-    pub fn new(players: Vec<PlayerProfile>, game_id: GameId, backend_db: BackendDb, ready_tx: oneshot::Sender<()>,) -> (Self, GameServerHandle) {
+    /// `players` includes the profiles of the bots
+    pub fn new(players: Vec<PlayerProfile>, bots: HashMap<PlayerId, BotDifficulty>, game_id: GameId, backend_db: BackendDb, ready_tx: oneshot::Sender<()>,) -> (Self, GameServerHandle) {
 
         let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
 
         let cards = backend_db.collect_cards().unwrap();
 
+        let mut game = Game::new(&players, cards);
+        // the seed is needed to reproduce the game
+        log::info!("Game {} created with seed {}", game_id, game.seed);
+        game.affinities = match load_affinity_table() {
+            Ok(affinities) => affinities,
+            Err(e) => {
+                log::error!("Error when loading the elemental affinities: {}", e);
+                None
+            }
+        };
+
         (
             Self {
-                sessions: Mutex::new(SessionsInner { sessions: HashMap::new() }),
-                game: Game::new(&players, cards),
+                sessions: Mutex::new(SessionsInner {
+                    sessions: HashMap::new(),
+                    spectators: HashMap::new(),
+                    // bots don't have sessions
+                    players: players.iter().map(|prf| prf.id).filter(|id| !bots.contains_key(id)).collect(),
+                    events: EventBuffer::new(),
+                }),
+                game,
                 accounts: players,
                 cmd_rx,
                 game_id,
                 backend_db,
                 ready_tx: Some(ready_tx),
+                replay: None,
+                bots,
+                disconnected: HashMap::new(),
+                bot_rng: GameRng::seed_from_u64(rand::random()),
             },
             GameServerHandle { cmd_tx },
         )
     }
 
+    /// Record a move accepted by the server in the replay
+    fn record_action(&mut self, player_id: PlayerId, author: MoveAuthor, action: UserAction) {
+        if let Some(replay) = &mut self.replay {
+            replay.record_action(player_id, author, action);
+        }
+    }
+
+    /// Record a response broadcasted to the players in the replay
+    fn record_event(&mut self, resp: &ServerResponse) {
+        if let Some(replay) = &mut self.replay {
+            replay.record_event(resp);
+        }
+    }
+
+    /// Store the replay of the game
+    fn save_replay(&mut self) {
+        if let Some(mut replay) = self.replay.take() {
+            replay.finish(&self.game);
+            if let Err(e) = self.backend_db.save_replay(&replay) {
+                log::error!("Error when saving the replay of game {}: {}", self.game_id, e);
+            }
+        }
+    }
+
     /// Send user message to others.
+    /// Messages of spectators are only sent to the other spectators.
     async fn send_chat_message_to_handlers(&self, conn: ConnId, msg: impl Into<Msg>) {
         let msg = msg.into();
-        let msg = ServerResponse::Message { message: msg };
-
-        for (conn_id, (_, tx)) in &self.sessions.lock().await.sessions {
-            if conn_id != &conn {
-                let _ = msg.send_unbounded(tx);
-            }
+        let inner = &mut *self.sessions.lock().await;
+
+        if let Some(spectator_id) = inner.spectators.get(&conn).map(|prf| prf.id) {
+            let msg = ServerResponse::SpectatorMessage { spectator_id, message: msg };
+            inner.send_each(|conn_id, _, is_spectator| {
+                if conn_id != Some(conn) && is_spectator { Some(msg.clone()) } else { None }
+            });
+        } else {
+            let msg = ServerResponse::Message { message: msg };
+            inner.send_each(|conn_id, _, _| {
+                if conn_id != Some(conn) { Some(msg.clone()) } else { None }
+            });
         }
     }
 
     async fn notify_game_started(&self) {
-        for (_, (player_id, tx)) in &self.sessions.lock().await.sessions {
-            let status = self.game.status_for_player(*player_id).unwrap();
+        self.sessions.lock().await.send_each(|_, player_id, is_spectator| {
+            let status = if is_spectator {
+                self.game.status_for_spectator().to_server_response()
+            } else {
+                self.game.status_for_player(player_id).unwrap().to_server_response()
+            };
+            Some(status)
+        });
+    }
 
-            let _ = status.to_server_response().send_unbounded(tx);
-        }
-        
+    /// Send the list of spectators to the clients
+    async fn notify_spectators_changed(&self) {
+        let inner = &mut *self.sessions.lock().await;
+        let resp = ServerResponse::SpectatorsChanged { spectators: inner.spectators.values().cloned().collect() };
+
+        inner.broadcast(&resp);
     }
 
-    async fn notify_game_end(&self, winner_id: PlayerId) {
-        for (_, (_, tx)) in &self.sessions.lock().await.sessions {
-            let resp = ServerResponse::GameEnd { winner_id };
-            let _ = resp.send_unbounded(tx);
-        }
+    async fn notify_game_end(&mut self, winner_id: PlayerId) {
+        let resp = ServerResponse::GameEnd { winner_id };
+        self.record_event(&resp);
+        self.save_replay();
+
+        self.sessions.lock().await.broadcast(&resp);
     }
 
     async fn send_game_state(&self, player_id: PlayerId) {
-        let sessions = &self.sessions.lock().await.sessions;
-
-        let tx = &sessions.iter().find(|(_, (id, _))| *id == player_id).unwrap().1.1;
+        // users that are not in the game are spectators
+        let resp = match self.game.status_for_player(player_id) {
+            Ok(state) => state.to_server_response(),
+            Err(_) => self.game.status_for_spectator().to_server_response(),
+        };
 
-        let state = self.game.status_for_player(player_id).unwrap();
-        let _ = state.to_server_response().send_unbounded(tx);
+        self.sessions.lock().await.send_to(player_id, resp);
     }
 
     async fn advance_turn(&mut self) {
+        if self.check_game_end().await {
+            return;
+        }
+
+        let events = self.game.advance_turn();
+        for event in events {
+            let resp = match event {
+                TurnEvent::Skipped { player_id, reason } => ServerResponse::TurnSkipped { player_id, reason },
+                TurnEvent::Tick { player_id, tick, action } => ServerResponse::StatusTick { player_id, tick, action },
+            };
+            self.record_event(&resp);
+            self.sessions.lock().await.broadcast(&resp);
+        }
+
+        // a player can be eliminated by a status tick
+        if self.check_game_end().await {
+            return;
+        }
+        self.notify_change_turn().await;
+
+        let current_player_id = self.game.current_player_id();
+        let mut card_count = self.game.players[self.game.current_player_turn].hand_cards.len();
+
+        // collect discard cards if needed
+        if card_count < DRAW_CARD_LIMIT && self.game.refill_pile(DRAW_CARD_LIMIT - card_count) {
+            let resp = ServerResponse::CollectDiscardCards { cards_in_pile: self.game.pile.len() as u32 };
+            self.record_event(&resp);
+
+            self.sessions.lock().await.broadcast(&resp);
+        }
+
+        // draw cards if player has less than 5 cards
+        while card_count < DRAW_CARD_LIMIT {
+            let card_id = self.game.draw_card(current_player_id).unwrap();
+            card_count += 1;
+            self.record_event(&ServerResponse::DrawCard { player_id: current_player_id, card_id });
+
+            self.sessions.lock().await.send_each(|_, pid, _| Some(ServerResponse::DrawCard {
+                player_id: current_player_id,
+                card_id: if pid == current_player_id { card_id } else { -1 },
+            }));
+        }
+    }
+
+    /// Notify the clients if the game is over.
+    /// Returns true if the game is over.
+    async fn check_game_end(&mut self) -> bool {
         match self.game.state {
             GameState::EndGame { winner_id } => {
                 self.notify_game_end(winner_id).await;
-                
+
                 // Reset ready status in the associated lobby
                 match self.backend_db.reset_users_ready_on_game_end(&self.game_id) {
                     Err(e) => { log::error!("Error when resetting users ready status on game end: {}", e.to_string()) }
                     _ => {}
                 }
-                return;
+                true
             }
-            _ => {}
+            _ => false
         }
+    }
 
-        self.game.advance_turn();
-        self.notify_change_turn().await;
+    /// Send the actions of a played card to the clients
+    async fn notify_card_played(&mut self, player_id: PlayerId, card_id: Option<CardId>, card_index: usize, turn_order: Order, play_info: PlayInfo) {
+        // list of buffs of the player
+        let buffs_resp = ServerResponse::PlayerBuffStatus {
+            player_id,
+            buffs: self.game.players[self.game.current_player_turn]
+                .buffs.iter()
+                .map(|b| b.as_status())
+                .collect()
+        };
+        // list of buffs of the players whose buffs were changed by the card
+        let changed_ids: Vec<PlayerId> = play_info.actions.iter()
+            .flat_map(|action| action.targets.iter())
+            .filter(|target| matches!(target.action, ActionType::Buff { .. } | ActionType::Debuff { .. } | ActionType::Dispel { .. }))
+            .map(|target| target.player_id)
+            .collect();
+        let targets_buffs_resp: Vec<ServerResponse> = self.game.players.iter()
+            .filter(|p| changed_ids.contains(&p.id))
+            .map(|p| ServerResponse::PlayerBuffStatus {
+                player_id: p.id,
+                buffs: p.buffs.iter().map(|b| b.as_status()).collect()
+            })
+            .collect();
+        let turn_order_resp = if self.game.turn_order != turn_order {
+            Some(ServerResponse::TurnOrderChanged { order: self.game.turn_order })
+        } else { None };
+        // the card stays in hand, at the last position
+        let return_resp = if play_info.return_to_hand {
+            Some(ServerResponse::ReturnCardToHand {
+                player_id,
+                card_id: card_id.unwrap_or(-1),
+                hand_index: self.game.players[self.game.current_player_turn].hand_cards.len() as u32 - 1,
+            })
+        } else { None };
+
+        // the replay keeps the hidden cards
+        self.record_event(&ServerResponse::PlayCard {
+            player_id,
+            card_id: card_id.unwrap_or(-1),
+            hand_index: card_index as u32,
+            actions: play_info.actions.clone(),
+        });
+        if let Some(return_resp) = &return_resp {
+            self.record_event(return_resp);
+        }
+        self.record_event(&buffs_resp);
+        for resp in targets_buffs_resp.iter() {
+            self.record_event(resp);
+        }
+        for target in play_info.actions.iter().flat_map(|action| action.targets.iter()) {
+            if let ActionType::Reveal { to, cards } = &target.action {
+                self.record_event(&ServerResponse::RevealHand { player_id: target.player_id, to: *to, cards: cards.clone() });
+            }
+        }
+        if let Some(turn_order_resp) = &turn_order_resp {
+            self.record_event(turn_order_resp);
+        }
 
-        let current_player_id = self.game.current_player_id();
-        let mut card_count = self.game.players[self.game.current_player_turn].hand_cards.len();
+        // send responses to clients
+        let inner = &mut *self.sessions.lock().await;
+        // hide the cards that the client is not allowed to see
+        inner.send_each(|_, pid, _| Some(ServerResponse::PlayCard {
+            player_id,
+            card_id: card_id.unwrap_or(-1),
+            hand_index: card_index as u32,
+            actions: play_info.redacted_for(pid).actions,
+        }));
+        if let Some(return_resp) = &return_resp {
+            inner.broadcast(return_resp);
+        }
+        inner.broadcast(&buffs_resp);
+        for resp in targets_buffs_resp.iter() {
+            inner.broadcast(resp);
+        }
+        // private reveals, only the receiver of the reveal sees the cards
+        for target in play_info.actions.iter().flat_map(|action| action.targets.iter()) {
+            if let ActionType::Reveal { .. } = target.action {
+                inner.send_each(|_, pid, _| match target.redacted_for(pid).action {
+                    ActionType::Reveal { to, cards } => Some(ServerResponse::RevealHand { player_id: target.player_id, to, cards }),
+                    _ => None
+                });
+            }
+        }
+        if let Some(turn_order_resp) = &turn_order_resp {
+            inner.broadcast(turn_order_resp);
+        }
+    }
 
-        // collect discard cards if needed
-        if card_count < DRAW_CARD_LIMIT && self.game.pile.len() < DRAW_CARD_LIMIT - card_count {
-            self.game.collect_discard_cards();
-            self.game.shuffle_pile();
-            let resp = ServerResponse::CollectDiscardCards { cards_in_pile: self.game.pile.len() as u32 };
+    /// Ask the current player to make a choice for the card being played
+    async fn notify_choice_required(&mut self) {
+        if let Some(pending) = &self.game.pending_choice {
+            let resp = ServerResponse::ChoiceRequired {
+                player_id: pending.player_id,
+                request: pending.request.clone(),
+                default: pending.default.clone(),
+                turn_end: self.game.current_player_turn_end,
+            };
+            self.record_event(&resp);
+            self.sessions.lock().await.broadcast(&resp);
+        }
+    }
+
+    /// Bot difficulty used for the player, if they are a bot or if they left the game
+    fn get_bot_difficulty(&self, player_id: PlayerId) -> Option<BotDifficulty> {
+        if let Some(difficulty) = self.bots.get(&player_id) {
+            return Some(*difficulty);
+        }
+
+        match self.disconnected.get(&player_id) {
+            Some(since) if since.elapsed() >= TAKEOVER_DELAY => Some(TAKEOVER_DIFFICULTY),
+            _ => None
+        }
+    }
 
-            for (_, (_, tx)) in &self.sessions.lock().await.sessions {
-                let _ = resp.send_unbounded(tx);
+    /// Play a move for the current player if it is controlled by a bot.
+    /// The turn is passed if the bot can't play.
+    async fn play_bot_move(&mut self) {
+        let player_id = self.game.current_player_id();
+        let difficulty = match self.get_bot_difficulty(player_id) {
+            Some(difficulty) => difficulty,
+            None => { return; }
+        };
+
+        // a choice left by the player before leaving
+        if self.game.pending_choice.is_some() {
+            let _ = self.resolve_choice(player_id, None, MoveAuthor::Bot).await;
+            return;
+        }
+
+        // the search can be long, run it outside of the async runtime on a copy of the game
+        let seed = self.bot_rng.random();
+        let game = self.game.clone();
+        let bot_move = tokio::task::spawn_blocking(move || difficulty.choose_move(&game, seed)).await
+            .unwrap_or_else(|err| {
+                log::error!("Bot {} move search failed: {}", player_id, err);
+                None
+            });
+
+        let played = match bot_move {
+            Some(BotMove::PlayCard { card_index, targets }) => {
+                let result = self.play_card(player_id, card_index, targets, MoveAuthor::Bot).await;
+                if result.is_ok() && self.game.pending_choice.is_some() {
+                    self.resolve_choice(player_id, None, MoveAuthor::Bot).await.map(|_| ())
+                } else {
+                    result.map(|_| ())
+                }
+            }
+            Some(BotMove::DrawCard) => self.draw_card(player_id, MoveAuthor::Bot).await.map(|_| ()),
+            Some(BotMove::EndTurn) => self.end_turn(player_id, MoveAuthor::Bot).await,
+            None => Err("No move".to_string())
+        };
+
+        if let Err(err) = played {
+            log::warn!("Bot {} can't play: {:?}", player_id, err);
+            if matches!(self.game.state, GameState::InGame) && self.game.current_player_id() == player_id {
+                self.auto_play_required_cards(MoveAuthor::Bot).await;
+                self.record_action(player_id, MoveAuthor::Bot, UserAction::EndTurn {});
+                self.advance_turn().await;
             }
         }
+    }
 
-        // draw cards if player has less than 5 cards
-        while card_count < DRAW_CARD_LIMIT {
-            let card_id = self.game.draw_card(current_player_id).unwrap();
-            card_count += 1;
+    /// Play a card of the player and notify the clients
+    async fn play_card(&mut self, player_id: PlayerId, card_index: usize, targets: Vec<PlayerId>, author: MoveAuthor) -> Result<PlayInfo, String> {
+        // get card id before it is removed from hand
+        let card_id = self.game.players
+            .iter()
+            .find(|p| p.id == player_id)
+            .and_then(|p| p.hand_cards.get(card_index))
+            .map(|c| c.get_id());
+
+        let turn_order = self.game.turn_order;
+        let result = self.game.play_card(player_id, card_index, targets.clone());
+
+        match &result {
+            Ok(play_info) => {
+                self.record_action(player_id, author, UserAction::PlayCard { card_index, targets });
+                if self.game.pending_choice.is_some() {
+                    // the card is played once the player made a choice
+                    self.notify_choice_required().await;
+                } else {
+                    self.notify_card_played(player_id, card_id, card_index, turn_order, play_info.clone()).await;
+                    self.end_play().await;
+                }
+            }
+            Err(err) => {
+                // send game state to player when error
+                println!("Error playing card: {:?}", err);
+                self.send_game_state(player_id).await;
+            }
+        }
 
-            for (_, (pid, tx)) in &self.sessions.lock().await.sessions {
-                let resp = ServerResponse::DrawCard {
-                    player_id: current_player_id,
-                    card_id: if *pid == current_player_id { card_id } else { -1 },
-                };
-                let _ = resp.send_unbounded(tx);
+        result
+    }
+
+    /// End a turn that allows more plays
+    async fn end_turn(&mut self, player_id: PlayerId, author: MoveAuthor) -> Result<(), String> {
+        let result = self.game.end_turn(player_id);
+
+        if let Err(err) = &result {
+            println!("Error ending turn: {:?}", err);
+            self.send_game_state(player_id).await;
+        } else {
+            self.record_action(player_id, author, UserAction::EndTurn {});
+            self.advance_turn().await;
+        }
+
+        result
+    }
+
+    /// Draw a card for the player, drawing a card ends the turn
+    async fn draw_card(&mut self, player_id: PlayerId, author: MoveAuthor) -> Result<CardId, String> {
+        let result = self.game.check_required_cards_played(player_id)
+            .and_then(|_| self.game.draw_card(player_id));
+
+        match &result {
+            Ok(card_id) => {
+                let card_id = *card_id;
+                self.record_action(player_id, author, UserAction::DrawCard {});
+                self.record_event(&ServerResponse::DrawCard { player_id, card_id });
+                self.sessions.lock().await.send_each(|_, pid, _| Some(ServerResponse::DrawCard {
+                    player_id,
+                    card_id: if pid == player_id { card_id } else { -1 },
+                }));
+                self.advance_turn().await;
+            }
+            Err(err) => {
+                // send game state to player when error
+                println!("Error drawing card: {:?}", err);
+                self.send_game_state(player_id).await;
             }
         }
+
+        result
     }
 
-    async fn notify_change_turn(&self) {
-        let resp = ServerResponse::ChangeTurn { player_id: self.game.current_player_id(), turn_end: self.game.current_player_turn_end };
-        for (_, (_, tx)) in &self.sessions.lock().await.sessions {
-            let _ = resp.send_unbounded(tx);
+    /// Resolve the choice of the current player (the default choice is used if `choice` is None) and play the card
+    async fn resolve_choice(&mut self, player_id: PlayerId, choice: Option<Choice>, author: MoveAuthor) -> Result<PlayInfo, String> {
+        let (card_index, default) = self.game.pending_choice.as_ref()
+            .filter(|pending| pending.player_id == player_id)
+            .map(|pending| (pending.card_index, pending.default.clone()))
+            .ok_or_else(|| "No choice to make".to_string())?;
+        let card_id = self.game.players[self.game.current_player_turn].hand_cards.get(card_index).map(|c| c.get_id());
+
+        let turn_order = self.game.turn_order;
+        let play_info = self.game.resolve_choice(player_id, choice.clone())?;
+        self.record_action(player_id, author, UserAction::ResolveChoice { choice: choice.unwrap_or(default) });
+
+        self.notify_card_played(player_id, card_id, card_index, turn_order, play_info.clone()).await;
+        self.end_play().await;
+
+        Ok(play_info)
+    }
+
+    /// Advance the turn after a card is played, unless the player can play more cards in this turn
+    async fn end_play(&mut self) {
+        if self.game.allows_more_plays() {
+            self.notify_continue_turn().await;
+        } else {
+            self.advance_turn().await;
+        }
+    }
+
+    /// Tell the clients that the current player can play more cards
+    async fn notify_continue_turn(&mut self) {
+        let player_id = self.game.current_player_id();
+        let required_cards: Vec<u32> = self.game.players[self.game.current_player_turn]
+            .required_cards().iter()
+            .map(|&idx| idx as u32)
+            .collect();
+        self.record_event(&ServerResponse::ContinueTurn {
+            player_id,
+            required_cards: required_cards.clone(),
+            turn_end: self.game.current_player_turn_end,
+        });
+
+        self.sessions.lock().await.send_each(|_, pid, _| Some(ServerResponse::ContinueTurn {
+            player_id,
+            required_cards: if pid == player_id { required_cards.clone() } else { Vec::new() },
+            turn_end: self.game.current_player_turn_end,
+        }));
+    }
+
+    /// Play the cards that the current player had to play in this turn (PlayAllCards buffs)
+    async fn auto_play_required_cards(&mut self, author: MoveAuthor) {
+        let player_id = self.game.current_player_id();
+        // cards returned to hand are not played again
+        let max_plays = self.game.players[self.game.current_player_turn].hand_cards.len();
+
+        for _ in 0..max_plays {
+            if !matches!(self.game.state, GameState::InGame) {
+                break;
+            }
+
+            let card_index = match self.game.players[self.game.current_player_turn].required_cards().first() {
+                Some(&idx) => idx,
+                None => break
+            };
+            let card_id = self.game.players[self.game.current_player_turn].hand_cards.get(card_index).map(|c| c.get_id());
+
+            let turn_order = self.game.turn_order;
+            match self.game.auto_play_card(player_id, card_index) {
+                Ok(auto_play) => {
+                    self.record_action(player_id, author, UserAction::PlayCard { card_index, targets: auto_play.targets });
+                    if let Some(choice) = auto_play.choice {
+                        self.record_action(player_id, author, UserAction::ResolveChoice { choice });
+                    }
+                    self.notify_card_played(player_id, card_id, card_index, turn_order, auto_play.play_info).await;
+                }
+                Err(err) => {
+                    println!("Error playing required card: {:?}", err);
+                    break;
+                }
+            }
         }
     }
 
+    async fn notify_change_turn(&mut self) {
+        let resp = ServerResponse::ChangeTurn { player_id: self.game.current_player_id(), turn_end: self.game.current_player_turn_end };
+        self.record_event(&resp);
+        self.sessions.lock().await.broadcast(&resp);
+    }
+
     /// Register new session and assign unique ID to this session
     async fn connect(&mut self, player_id: PlayerId, tx: mpsc::UnboundedSender<Msg>) -> ConnId {
         log::info!("Someone joined");
@@ -238,18 +739,57 @@ impl GameServer {
             sessions.insert(id, (player_id, tx));
         }
 
+        // the player plays again if a bot took over
+        self.disconnected.remove(&player_id);
+
         // send id back
         id
     }
 
+    /// Register the session of a spectator
+    async fn connect_spectator(&mut self, profile: PlayerProfile, tx: mpsc::UnboundedSender<Msg>) -> ConnId {
+        log::info!("Spectator {} joined", profile.id);
+
+        // stop connection associated with this spectator (if any)
+        let maybe_conn_id = {
+            let sessions = &self.sessions.lock().await.sessions;
+            sessions.iter().find(|(_, (id, _))| *id == profile.id).map(|(id, _)| *id)
+        };
+
+        if let Some(conn_id) = maybe_conn_id {
+            self.disconnect(conn_id).await;
+        }
+
+        let id = IdU64::<ConnId>::new().get();
+
+        {
+            let inner = &mut self.sessions.lock().await;
+            inner.sessions.insert(id, (profile.id, tx));
+            inner.spectators.insert(id, profile);
+        }
+
+        self.notify_spectators_changed().await;
+
+        id
+    }
+
     /// Unregister connection from room map and broadcast disconnection message.
     async fn disconnect(&mut self, conn_id: ConnId) {
-        let sessions = &mut self.sessions.lock().await.sessions;
+        let spectator_left = {
+            let inner = &mut self.sessions.lock().await;
+
+            // remove sender
+            if let Some((player_id, _)) = inner.sessions.remove(&conn_id) {
+                println!("Session {conn_id:?} disconnected");
+                if !inner.spectators.contains_key(&conn_id) {
+                    self.disconnected.insert(player_id, Instant::now());
+                }
+            }
+            inner.spectators.remove(&conn_id).is_some()
+        };
 
-        // remove sender
-        if sessions.remove(&conn_id).is_some() {
-            println!("Session {conn_id:?} disconnected");
-            // extra stuff
+        if spectator_left {
+            self.notify_spectators_changed().await;
         }
     }
 
@@ -279,7 +819,7 @@ impl GameServer {
 
                 Either::Left((Some(cmd), _)) => {
                     match cmd {
-                        Command::Connect { player_id, conn_tx, res_tx } => {
+                        Command::Connect { player_id, last_seq, conn_tx, res_tx } => {
                             let conn_id = self.connect(player_id, conn_tx).await;
                             let _ = res_tx.send(conn_id);
         
@@ -287,10 +827,31 @@ impl GameServer {
                                 // not yet started
                                 GameState::PreGame => {
                                     self.game.begin();
+                                    self.replay = Some(Replay::new(self.game_id, &self.game));
+                                    // a bot plays for the players who don't join the game in time
+                                    let connected = self.sessions.lock().await.sessions.values()
+                                        .map(|(id, _)| *id)
+                                        .collect::<Vec<PlayerId>>();
+                                    for player in self.game.players.iter() {
+                                        if !self.bots.contains_key(&player.id) && !connected.contains(&player.id) {
+                                            self.disconnected.insert(player.id, Instant::now());
+                                        }
+                                    }
                                     self.notify_game_started().await;
                                 }
                                 GameState::InGame => {
-                                    self.send_game_state(player_id).await;
+                                    // send the missed responses, or the game state if they are no longer available
+                                    let resumed = match last_seq {
+                                        Some(last_seq) => self.sessions.lock().await.resend_missed_events(player_id, last_seq),
+                                        None => false
+                                    };
+                                    if !resumed {
+                                        self.send_game_state(player_id).await;
+                                        self.notify_choice_required().await;
+                                        if self.game.allows_more_plays() {
+                                            self.notify_continue_turn().await;
+                                        }
+                                    }
                                 }
                                 // finished
                                 // should not happen as we exit the recv loop
@@ -302,6 +863,17 @@ impl GameServer {
                             }
                         }
         
+                        Command::ConnectSpectator { profile, conn_tx, res_tx } => {
+                            let spectator_id = profile.id;
+                            let conn_id = self.connect_spectator(profile, conn_tx).await;
+                            let _ = res_tx.send(conn_id);
+
+                            // the game status is sent when the game begins
+                            if let GameState::InGame = self.game.state {
+                                self.send_game_state(spectator_id).await;
+                            }
+                        }
+
                         Command::Disconnect { conn } => {
                             self.disconnect(conn).await;
                         }
@@ -310,6 +882,11 @@ impl GameServer {
                             let players = self.game.player_profiles.clone();
                             let _ = res_tx.send(players);
                         }
+
+                        Command::SpectatorList { res_tx } => {
+                            let spectators = self.sessions.lock().await.spectators.values().cloned().collect();
+                            let _ = res_tx.send(spectators);
+                        }
         
                         Command::PlayCard { player_id, card_index, targets, res_tx } => {
                             // match self.game.state {
@@ -322,47 +899,43 @@ impl GameServer {
                             //     _ => {}
                             // }
         
-                            // get card id before it is removed from hand
-                            let card_id = self.game.players
-                                .iter()
-                                .find(|p| p.id == player_id)
-                                .and_then(|p| p.hand_cards.get(card_index))
-                                .map(|c| c.get_id());
+                            let result = self.play_card(player_id, card_index, targets, MoveAuthor::Player).await;
+                            let _ = res_tx.send(result);
+                        }
         
-                            let result = self.game.play_card(player_id, card_index, targets.clone());
-                            let ok = result.is_ok();
+                        Command::ResolveChoice { player_id, choice, res_tx } => {
+                            let result = self.resolve_choice(player_id, Some(choice), MoveAuthor::Player).await;
                             let _ = res_tx.send(result.clone());
-        
-                            if ok {
-                                let play_info = result.unwrap();
-
-                                let resp = ServerResponse::PlayCard {
-                                    player_id,
-                                    card_id: card_id.unwrap_or(-1),
-                                    hand_index: card_index as u32,
-                                    actions: play_info.actions.clone(),
-                                };
-                                // list of buffs of the player
-                                let buffs_resp = ServerResponse::PlayerBuffStatus {
-                                    player_id,
-                                    buffs: self.game.players[self.game.current_player_turn]
-                                        .buffs.iter()
-                                        .map(|b| b.as_variant())
-                                        .collect()
-                                };
-                                // send responses to clients
-                                for (_, (_, tx)) in &self.sessions.lock().await.sessions {
-                                    let _ = resp.send_unbounded(tx);
-                                    let _ = buffs_resp.send_unbounded(tx);
-                                }
-                                self.advance_turn().await;
-                            } else {
+
+                            if let Err(err) = result {
                                 // send game state to player when error
-                                println!("Error playing card: {:?}", result.clone().err().unwrap());
+                                println!("Error resolving choice: {:?}", err);
                                 self.send_game_state(player_id).await;
+                                // ask again
+                                self.notify_choice_required().await;
                             }
                         }
-        
+
+                        Command::EndTurn { player_id, res_tx } => {
+                            let result = self.end_turn(player_id, MoveAuthor::Player).await;
+                            let _ = res_tx.send(result);
+                        }
+
+                        Command::ChooseElement { player_id, element, res_tx } => {
+                            let result = self.game.choose_element(player_id, element);
+                            let _ = res_tx.send(result.clone());
+
+                            if let Err(err) = result {
+                                println!("Error choosing element: {:?}", err);
+                                self.send_game_state(player_id).await;
+                            } else {
+                                self.record_action(player_id, MoveAuthor::Player, UserAction::ChooseElement { element });
+                                let resp = ServerResponse::ElementChosen { player_id, element };
+                                self.record_event(&resp);
+                                self.sessions.lock().await.broadcast(&resp);
+                            }
+                        }
+
                         Command::DrawCard { player_id, res_tx } => {
                             // match self.game.state {
                             //     // should not happen as we exit the recv loop
@@ -374,25 +947,8 @@ impl GameServer {
                             //     _ => {}
                             // }
                             
-                            let result = self.game.draw_card(player_id);
-                            let ok = result.is_ok();
-                            let card_id = result.clone().unwrap_or(-1);
-                            let _ = res_tx.send(result.clone());
-        
-                            if ok {
-                                for (_, (pid, tx)) in &self.sessions.lock().await.sessions {
-                                    let resp = ServerResponse::DrawCard {
-                                        player_id,
-                                        card_id: if *pid == player_id { card_id } else { -1 },
-                                    };
-                                    let _ = resp.send_unbounded(tx);
-                                }
-                                self.advance_turn().await;
-                            } else {
-                                // send game state to player when error
-                                println!("Error drawing card: {:?}", result.clone().err().unwrap());
-                                self.send_game_state(player_id).await;
-                            }
+                            let result = self.draw_card(player_id, MoveAuthor::Player).await;
+                            let _ = res_tx.send(result);
                         }
         
                         Command::Message { conn, msg, res_tx } => {
@@ -424,7 +980,24 @@ impl GameServer {
                     match self.game.state {
                         GameState::InGame => {
                             if self.game.current_player_turn_end < Utc::now() {
-                                self.advance_turn().await;
+                                if self.game.pending_choice.is_some() {
+                                    // play the card with the default choice
+                                    let current_player_id = self.game.current_player_id();
+                                    if let Err(err) = self.resolve_choice(current_player_id, None, MoveAuthor::Server).await {
+                                        println!("Error resolving default choice: {:?}", err);
+                                        self.game.pending_choice = None;
+                                    }
+                                }
+                                // the turn is already over if the card ended it
+                                if matches!(self.game.state, GameState::InGame) && self.game.current_player_turn_end < Utc::now() {
+                                    self.auto_play_required_cards(MoveAuthor::Server).await;
+                                    let current_player_id = self.game.current_player_id();
+                                    self.record_action(current_player_id, MoveAuthor::Server, UserAction::EndTurn {});
+                                    self.advance_turn().await;
+                                }
+                            } else {
+                                // bots play one move per tick
+                                self.play_bot_move().await;
                             }
                         }
                         _ => {}
@@ -435,14 +1008,17 @@ impl GameServer {
         }
 
         {
-            let sessions = &mut self.sessions.lock().await.sessions;
-            sessions.clear();
+            let inner = &mut self.sessions.lock().await;
+            inner.sessions.clear();
+            inner.spectators.clear();
         }
 
         if !cmd_rx.is_closed() {
             cmd_rx.close();
         }
 
+        self.save_replay();
+
         log::info!("GameServer worker stopped (game ended)");
         
         Ok(())
@@ -458,12 +1034,26 @@ pub struct GameServerHandle {
 
 impl GameServerHandle {
     /// Register client message sender and obtain connection ID.
-    pub async fn connect(&self, player_id: PlayerId, conn_tx: mpsc::UnboundedSender<Msg>) -> ConnId {
+    /// `last_seq` is the sequence number of the last response received by a client that reconnects.
+    pub async fn connect(&self, player_id: PlayerId, last_seq: Option<Seq>, conn_tx: mpsc::UnboundedSender<Msg>) -> ConnId {
+        let (res_tx, res_rx) = oneshot::channel();
+
+        // unwrap: game server should not have been dropped
+        self.cmd_tx
+            .send(Command::Connect { player_id, last_seq, conn_tx, res_tx })
+            .unwrap();
+
+        // unwrap: game server does not drop out response channel
+        res_rx.await.unwrap()
+    }
+
+    /// Register the message sender of a spectator and obtain connection ID.
+    pub async fn connect_spectator(&self, profile: PlayerProfile, conn_tx: mpsc::UnboundedSender<Msg>) -> ConnId {
         let (res_tx, res_rx) = oneshot::channel();
 
         // unwrap: game server should not have been dropped
         self.cmd_tx
-            .send(Command::Connect { player_id, conn_tx, res_tx })
+            .send(Command::ConnectSpectator { profile, conn_tx, res_tx })
             .unwrap();
 
         // unwrap: game server does not drop out response channel
@@ -501,6 +1091,20 @@ impl GameServerHandle {
         res_rx.await.unwrap()
     }
 
+    pub async fn get_spectators(&self) -> Vec<PlayerProfile> {
+        let (res_tx, res_rx) = oneshot::channel();
+
+        // unwrap: game server should not have been dropped
+        self.cmd_tx
+            .send(Command::SpectatorList {
+                res_tx,
+            })
+            .unwrap();
+
+        // unwrap: game server does not drop our response channel
+        res_rx.await.unwrap()
+    }
+
     pub async fn send_game_state_for_player(&self, player_id: PlayerId) {
         let (res_tx, res_rx) = oneshot::channel();
 
@@ -532,6 +1136,53 @@ impl GameServerHandle {
         res_rx.await.unwrap()
     }
 
+    pub async fn send_resolve_choice_action(&self, player_id: PlayerId, choice: Choice) -> Result<PlayInfo, String> {
+        let (res_tx, res_rx) = oneshot::channel();
+
+        // unwrap: game server should not have been dropped
+        self.cmd_tx
+            .send(Command::ResolveChoice {
+                player_id,
+                choice,
+                res_tx,
+            })
+            .unwrap();
+
+        // unwrap: game server does not drop our response channel
+        res_rx.await.unwrap()
+    }
+
+    pub async fn send_end_turn_action(&self, player_id: PlayerId) -> Result<(), String> {
+        let (res_tx, res_rx) = oneshot::channel();
+
+        // unwrap: game server should not have been dropped
+        self.cmd_tx
+            .send(Command::EndTurn {
+                player_id,
+                res_tx,
+            })
+            .unwrap();
+
+        // unwrap: game server does not drop our response channel
+        res_rx.await.unwrap()
+    }
+
+    pub async fn send_choose_element_action(&self, player_id: PlayerId, element: Element) -> Result<(), String> {
+        let (res_tx, res_rx) = oneshot::channel();
+
+        // unwrap: game server should not have been dropped
+        self.cmd_tx
+            .send(Command::ChooseElement {
+                player_id,
+                element,
+                res_tx,
+            })
+            .unwrap();
+
+        // unwrap: game server does not drop our response channel
+        res_rx.await.unwrap()
+    }
+
     pub async fn send_draw_card_action(&self, player_id: PlayerId) -> Result<CardId, String> {
         let (res_tx, res_rx) = oneshot::channel();
 